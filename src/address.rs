@@ -1,7 +1,415 @@
-struct Address {
+use std::fmt;
+use std::str::FromStr;
+
+use crate::base58::{Base58, Base58Error};
+use crate::bech32::{Bech32, Bech32Data, Bech32Error, Variant};
+use crate::network::Network;
+use crate::utils;
+
+// https://en.bitcoin.it/wiki/List_of_address_prefixes
+// https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+// https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+
+/// An address someone can be paid to, along with the network it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Address {
+    network: Network,
     address: AddressType,
 }
 
-enum AddressType {
+/// The kinds of output an address can stand for, each holding the hash or
+/// witness program that the address carries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AddressType {
+    /// HASH160 of a public key.
+    P2pkh([u8; 20]),
+    /// HASH160 of a redeem script.
+    P2sh([u8; 20]),
+    /// Segwit v0 HASH160 of a compressed public key.
+    P2wpkh([u8; 20]),
+    /// Segwit v0 SHA256 of a witness script.
+    P2wsh([u8; 32]),
+    /// Segwit v1 x-only output key.
+    P2tr([u8; 32]),
+    /// A witness version and program with no output type defined for them
+    /// yet. They can still be paid to, so that wallets don't have to be
+    /// upgraded before a soft fork gives them a meaning.
+    WitnessUnknown { version: u8, program: Vec<u8> },
+}
+
+impl fmt::Display for AddressType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::P2pkh(_) => write!(f, "P2PKH"),
+            Self::P2sh(_) => write!(f, "P2SH"),
+            Self::P2wpkh(_) => write!(f, "P2WPKH"),
+            Self::P2wsh(_) => write!(f, "P2WSH"),
+            Self::P2tr(_) => write!(f, "P2TR"),
+            Self::WitnessUnknown { .. } => write!(f, "unknown segwit"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum AddressError {
+    Base58(Base58Error),
+    Bech32(Bech32Error),
+    /// The Base58Check version byte isn't a P2PKH or P2SH one we know of.
+    UnknownVersion(u8),
+    /// Looks like a bech32 address, but the HRP isn't one of a known network.
+    UnknownHrp(String),
+    /// Base58 addresses carry a 20 byte hash.
+    InvalidHashLength(usize),
+    /// There was nothing after the separator to take the witness version from.
+    MissingWitnessVersion,
+    /// Witness versions only go up to 16.
+    InvalidWitnessVersion(u8),
+    /// Version 0 programs are 20 or 32 bytes, and later ones 2 to 40.
+    InvalidWitnessProgramLength { version: u8, length: usize },
+    /// Version 0 programs use the bech32 checksum and later ones bech32m.
+    WrongChecksumVariant { version: u8 },
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Base58(error) => write!(f, "invalid base58 address: {}", error),
+            Self::Bech32(error) => write!(f, "invalid bech32 address: {}", error),
+            Self::UnknownVersion(version) => write!(f, "0x{:02x} isn't a known address version", version),
+            Self::UnknownHrp(hrp) => write!(f, "'{}' isn't a known network's human readable part", hrp),
+            Self::InvalidHashLength(length) => write!(f, "{} bytes is the wrong length for an address hash", length),
+            Self::MissingWitnessVersion => write!(f, "the witness version is missing"),
+            Self::InvalidWitnessVersion(version) => write!(f, "{} isn't a valid witness version", version),
+            Self::InvalidWitnessProgramLength { version, length } => write!(f, "{} bytes is the wrong length for a version {} witness program", length, version),
+            Self::WrongChecksumVariant { version } => write!(f, "the wrong checksum was used for witness version {}", version),
+        }
+    }
+}
+
+impl From<Base58Error> for AddressError {
+    fn from(error: Base58Error) -> Self {
+        Self::Base58(error)
+    }
+}
+
+impl From<Bech32Error> for AddressError {
+    fn from(error: Bech32Error) -> Self {
+        Self::Bech32(error)
+    }
+}
+
+impl Address {
+    pub(crate) fn new(address: AddressType, network: Network) -> Self {
+        Self { network, address }
+    }
+
+    /// Pays to the HASH160 of a compressed public key under witness version 0.
+    pub(crate) fn p2wpkh(compressed_public_key: &[u8; 33], network: Network) -> Self {
+        Self::new(AddressType::P2wpkh(utils::hash160(compressed_public_key)), network)
+    }
+
+    pub(crate) fn network(&self) -> Network {
+        self.network
+    }
+
+    pub(crate) fn address_type(&self) -> AddressType {
+        self.address.clone()
+    }
+
+    /// The hash the address pays to. Taproot pays to a key rather than a hash,
+    /// and an unknown witness program could be anything, so they have none.
+    pub(crate) fn hash(&self) -> Option<&[u8]> {
+        match &self.address {
+            AddressType::P2pkh(hash) | AddressType::P2sh(hash) | AddressType::P2wpkh(hash) => Some(hash),
+            AddressType::P2wsh(hash) => Some(hash),
+            AddressType::P2tr(_) | AddressType::WitnessUnknown { .. } => None,
+        }
+    }
+
+    /// The witness version of a segwit address.
+    pub(crate) fn witness_version(&self) -> Option<u8> {
+        match self.address {
+            AddressType::P2pkh(_) | AddressType::P2sh(_) => None,
+            AddressType::P2wpkh(_) | AddressType::P2wsh(_) => Some(0),
+            AddressType::P2tr(_) => Some(1),
+            AddressType::WitnessUnknown { version, .. } => Some(version),
+        }
+    }
+
+    /// The witness program of a segwit address.
+    pub(crate) fn witness_program(&self) -> Option<&[u8]> {
+        match &self.address {
+            AddressType::P2pkh(_) | AddressType::P2sh(_) => None,
+            AddressType::P2wpkh(program) => Some(program),
+            AddressType::P2wsh(program) | AddressType::P2tr(program) => Some(program),
+            AddressType::WitnessUnknown { program, .. } => Some(program),
+        }
+    }
+
+    fn from_base58(address: &str) -> Result<Self, AddressError> {
+        let decoded = Base58::check_decode(address)?;
+        let hash = || decoded.payload.as_slice().try_into()
+            .map_err(|_| AddressError::InvalidHashLength(decoded.payload.len()));
+        if let Some(network) = Network::from_p2pkh_version(decoded.version) {
+            return Ok(Self::new(AddressType::P2pkh(hash()?), network))
+        }
+        if let Some(network) = Network::from_p2sh_version(decoded.version) {
+            return Ok(Self::new(AddressType::P2sh(hash()?), network))
+        }
+        Err(AddressError::UnknownVersion(decoded.version))
+    }
+
+    fn from_bech32(address: &str, network: Network) -> Result<Self, AddressError> {
+        let Bech32Data { data, variant, .. } = Bech32::decode(address)?;
+        let (&version, program) = data.split_first().ok_or(AddressError::MissingWitnessVersion)?;
+        if version > 16 {
+            return Err(AddressError::InvalidWitnessVersion(version))
+        }
+        let expected_variant = if version == 0 { Variant::Bech32 } else { Variant::Bech32m };
+        if variant != expected_variant {
+            return Err(AddressError::WrongChecksumVariant { version })
+        }
+        let program = Bech32::from_base32(program)?;
+        let invalid_length = AddressError::InvalidWitnessProgramLength { version, length: program.len() };
+        let address = match (version, program.len()) {
+            (0, 20) => AddressType::P2wpkh(program.try_into().unwrap()),
+            (0, 32) => AddressType::P2wsh(program.try_into().unwrap()),
+            (0, _) => return Err(invalid_length),
+            (1, 32) => AddressType::P2tr(program.try_into().unwrap()),
+            (_, 2..=40) => AddressType::WitnessUnknown { version, program },
+            _ => return Err(invalid_length),
+        };
+        Ok(Self::new(address, network))
+    }
+}
 
-}
\ No newline at end of file
+impl FromStr for Address {
+    type Err = AddressError;
+
+    /// Works out the type and network of an address from its prefix. Signet
+    /// shares testnet's prefixes, and regtest shares its base58 ones, so those
+    /// addresses come back as testnet ones.
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        // Bech32 puts its HRP before the last '1'. Base58 addresses can have
+        // a '1' in them too, but never start with a known HRP followed by it.
+        if let Some((hrp, _)) = address.rsplit_once('1') {
+            let hrp = hrp.to_lowercase();
+            if let Some(network) = Network::from_bech32_hrp(&hrp) {
+                return Self::from_bech32(address, network)
+            }
+            // A base58 address could have bech32's shape by chance, so only
+            // blame the HRP if it isn't base58 either.
+            if Bech32::is_bech32_shaped(address) {
+                return Self::from_base58(address).map_err(|_| AddressError::UnknownHrp(hrp))
+            }
+        }
+        Self::from_base58(address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.address {
+            AddressType::P2pkh(hash) => write!(f, "{}", Base58::check_encode(self.network.p2pkh_version(), hash)),
+            AddressType::P2sh(hash) => write!(f, "{}", Base58::check_encode(self.network.p2sh_version(), hash)),
+            _ => {
+                let version = self.witness_version().unwrap();
+                let variant = if version == 0 { Variant::Bech32 } else { Variant::Bech32m };
+                let data = [&[version][..], &Bech32::to_base32(self.witness_program().unwrap())].concat();
+                let address = Bech32::encode(self.network.bech32_hrp(), &data, variant)
+                    .expect("the network HRPs are valid");
+                write!(f, "{}", address)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    fn hex_to_vec(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn assert_round_trip(text: &str, network: Network, address_type: AddressType) {
+        let address = Address::from_str(text).unwrap();
+        assert_eq!(address.network(), network);
+        assert_eq!(address.address_type(), address_type);
+        // Segwit addresses may be given in upper case but are shown in lower case.
+        if address.witness_version().is_some() {
+            assert_eq!(address.to_string(), text.to_lowercase());
+        } else {
+            assert_eq!(address.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_p2pkh() {
+        let hash = hex_to_vec("f5f2d624cfb5c3f66d06123d0829d1c9cebf770e").try_into().unwrap();
+        assert_round_trip("1PRTTaJesdNovgne6Ehcdu1fpEdX7913CK", Network::Mainnet, AddressType::P2pkh(hash));
+        let address = Address::from_str("1PRTTaJesdNovgne6Ehcdu1fpEdX7913CK").unwrap();
+        assert_eq!(address.hash(), Some(&hash[..]));
+        assert_eq!(address.witness_version(), None);
+        assert_eq!(address.witness_program(), None);
+
+        let hash = hex_to_vec("2450abe3830d8508b69edf22964582b78ccc45cc").try_into().unwrap();
+        assert_round_trip("mipyG7KoQ4VGR2TqV5qmcdZJ3gjwPb7cRi", Network::Testnet, AddressType::P2pkh(hash));
+    }
+
+    #[test]
+    fn test_p2sh() {
+        let hash = hex_to_vec("b472a266d0bd89c13706a4132ccfb16f7c3b9fcb").try_into().unwrap();
+        assert_round_trip("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy", Network::Mainnet, AddressType::P2sh(hash));
+        assert_round_trip("2N9hLwkSqr1cPQAPxbrGVUjxyjD11G2e1he", Network::Testnet, AddressType::P2sh(hash));
+    }
+
+    #[test]
+    fn test_segwit() {
+        // BIP350's valid addresses for the versions we support.
+        let hash = hex_to_vec("751e76e8199196d454941c45d1b3a323f1433bd6");
+        assert_round_trip("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4", Network::Mainnet,
+            AddressType::P2wpkh(hash.clone().try_into().unwrap()));
+        let address = Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap();
+        assert_eq!(address.hash(), Some(&hash[..]));
+        assert_eq!(address.witness_version(), Some(0));
+        assert_eq!(address.witness_program(), Some(&hash[..]));
+
+        let hash = hex_to_vec("1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262");
+        assert_round_trip("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7", Network::Testnet,
+            AddressType::P2wsh(hash.try_into().unwrap()));
+        let hash = hex_to_vec("000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433");
+        assert_round_trip("tb1qqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesrxh6hy", Network::Testnet,
+            AddressType::P2wsh(hash.clone().try_into().unwrap()));
+        assert_round_trip("tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c", Network::Testnet,
+            AddressType::P2tr(hash.try_into().unwrap()));
+
+        let key = hex_to_vec("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
+        assert_round_trip("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0", Network::Mainnet,
+            AddressType::P2tr(key.clone().try_into().unwrap()));
+        let address = Address::from_str("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0").unwrap();
+        assert_eq!(address.hash(), None);
+        assert_eq!(address.witness_version(), Some(1));
+        assert_eq!(address.witness_program(), Some(&key[..]));
+    }
+
+    #[test]
+    fn test_regtest() {
+        let hash = hex_to_vec("751e76e8199196d454941c45d1b3a323f1433bd6");
+        assert_round_trip("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080", Network::Regtest,
+            AddressType::P2wpkh(hash.try_into().unwrap()));
+        let key = hex_to_vec("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
+        assert_round_trip("bcrt1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqc8gma6", Network::Regtest,
+            AddressType::P2tr(key.try_into().unwrap()));
+
+        // Regtest's base58 addresses can't be told apart from testnet's.
+        let wallet_hash = hex_to_vec("2450abe3830d8508b69edf22964582b78ccc45cc").try_into().unwrap();
+        let address = Address::new(AddressType::P2pkh(wallet_hash), Network::Regtest);
+        assert_eq!(address.to_string(), "mipyG7KoQ4VGR2TqV5qmcdZJ3gjwPb7cRi");
+        assert_eq!(Address::from_str(&address.to_string()).unwrap().network(), Network::Testnet);
+    }
+
+    #[test]
+    fn test_unknown_segwit() {
+        // BIP350's valid addresses for programs with nothing defined for them yet.
+        let program = hex_to_vec("751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6");
+        assert_round_trip("bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y", Network::Mainnet,
+            AddressType::WitnessUnknown { version: 1, program });
+        assert_round_trip("BC1SW50QGDZ25J", Network::Mainnet,
+            AddressType::WitnessUnknown { version: 16, program: hex_to_vec("751e") });
+        let program = hex_to_vec("751e76e8199196d454941c45d1b3a323");
+        assert_round_trip("bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs", Network::Mainnet,
+            AddressType::WitnessUnknown { version: 2, program: program.clone() });
+
+        let address = Address::from_str("bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs").unwrap();
+        assert_eq!(address.hash(), None);
+        assert_eq!(address.witness_version(), Some(2));
+        assert_eq!(address.witness_program(), Some(&program[..]));
+    }
+
+    #[test]
+    fn test_invalid_segwit() {
+        // BIP350's invalid addresses
+        assert_eq!(Address::from_str("tc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq5zuyut"),
+            Err(AddressError::UnknownHrp("tc".to_string())));
+        assert_eq!(Address::from_str("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd"),
+            Err(AddressError::WrongChecksumVariant { version: 1 }));
+        assert_eq!(Address::from_str("tb1z0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqglt7rf"),
+            Err(AddressError::WrongChecksumVariant { version: 2 }));
+        assert_eq!(Address::from_str("BC1S0XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ54WELL"),
+            Err(AddressError::WrongChecksumVariant { version: 16 }));
+        assert_eq!(Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh"),
+            Err(AddressError::WrongChecksumVariant { version: 0 }));
+        assert_eq!(Address::from_str("tb1q0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq24jc47"),
+            Err(AddressError::WrongChecksumVariant { version: 0 }));
+        assert_eq!(Address::from_str("bc1p38j9r5y49hruaue7wxjce0updqjuyyx0kh56v8s25huc6995vvpql3jow4"),
+            Err(AddressError::Bech32(Bech32Error::InvalidCharacter { character: 'o', position: 59 })));
+        assert_eq!(Address::from_str("BC130XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ7ZWS8R"),
+            Err(AddressError::InvalidWitnessVersion(17)));
+        assert_eq!(Address::from_str("bc1pw5dgrnzv"),
+            Err(AddressError::InvalidWitnessProgramLength { version: 1, length: 1 }));
+        assert_eq!(Address::from_str("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v8n0nx0muaewav253zgeav"),
+            Err(AddressError::InvalidWitnessProgramLength { version: 1, length: 41 }));
+        assert_eq!(Address::from_str("BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P"),
+            Err(AddressError::InvalidWitnessProgramLength { version: 0, length: 16 }));
+        assert_eq!(Address::from_str("tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47Zagq"),
+            Err(AddressError::Bech32(Bech32Error::MixedCase)));
+        assert_eq!(Address::from_str("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v07qwwzcrf"),
+            Err(AddressError::Bech32(Bech32Error::InvalidPadding)));
+        assert_eq!(Address::from_str("tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vpggkg4j"),
+            Err(AddressError::Bech32(Bech32Error::InvalidPadding)));
+        assert_eq!(Address::from_str("bc1gmk9yu"), Err(AddressError::MissingWitnessVersion));
+    }
+
+    #[test]
+    fn test_bip173_addresses() {
+        // BIP173's valid addresses for versions above 0 used bech32, which
+        // BIP350 replaced with bech32m for them.
+        assert_eq!(Address::from_str("bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7k7grplx"),
+            Err(AddressError::WrongChecksumVariant { version: 1 }));
+        assert_eq!(Address::from_str("BC1SW50QA3JX3S"), Err(AddressError::WrongChecksumVariant { version: 16 }));
+        assert_eq!(Address::from_str("bc1zw508d6qejxtdg4y5r3zarvaryvg6kdaj"), Err(AddressError::WrongChecksumVariant { version: 2 }));
+
+        // BIP173's invalid addresses
+        assert_eq!(Address::from_str("tc1qw508d6qejxtdg4y5r3zarvary0c5xw7kg3g4ty"),
+            Err(AddressError::UnknownHrp("tc".to_string())));
+        assert_eq!(Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"),
+            Err(AddressError::Bech32(Bech32Error::InvalidChecksum { typos: vec![41] })));
+        assert_eq!(Address::from_str("BC13W508D6QEJXTDG4Y5R3ZARVARY0C5XW7KN40WF2"), Err(AddressError::InvalidWitnessVersion(17)));
+        assert_eq!(Address::from_str("bc1rw5uspcuh"), Err(AddressError::WrongChecksumVariant { version: 3 }));
+        assert_eq!(Address::from_str("bc10w508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kw5rljs90"),
+            Err(AddressError::WrongChecksumVariant { version: 15 }));
+        assert_eq!(Address::from_str("BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P"),
+            Err(AddressError::InvalidWitnessProgramLength { version: 0, length: 16 }));
+        assert_eq!(Address::from_str("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sL5k7"),
+            Err(AddressError::Bech32(Bech32Error::MixedCase)));
+        assert_eq!(Address::from_str("bc1zw508d6qejxtdg4y5r3zarvaryvqyzf3du"), Err(AddressError::WrongChecksumVariant { version: 2 }));
+        assert_eq!(Address::from_str("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3pjxtptv"),
+            Err(AddressError::Bech32(Bech32Error::InvalidPadding)));
+        assert_eq!(Address::from_str("bc1gmk9yu"), Err(AddressError::MissingWitnessVersion));
+    }
+
+    #[test]
+    fn test_unknown_hrp() {
+        // a typo in the HRP, in either case
+        assert_eq!(Address::from_str("bx1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+            Err(AddressError::UnknownHrp("bx".to_string())));
+        assert_eq!(Address::from_str("BX1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4"),
+            Err(AddressError::UnknownHrp("bx".to_string())));
+        // mixed case isn't bech32, so it's taken as base58
+        assert_eq!(Address::from_str("bX1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+            Err(AddressError::Base58(Base58Error::InvalidCharacter { character: '0', position: 6 })));
+    }
+
+    #[test]
+    fn test_invalid_base58() {
+        // A WIF private key is Base58Check too, but not an address.
+        assert_eq!(Address::from_str("5JG9hT3beGTJuUAmCQEmNaxAuMacCTfXuw1R3FCXig23RQHMr4K"),
+            Err(AddressError::UnknownVersion(0x80)));
+        assert_eq!(Address::from_str("1PRTTaJesdNovgne6Ehcdu1fpEdX7913CL"),
+            Err(AddressError::Base58(Base58Error::InvalidChecksum)));
+        let address = Base58::check_encode(0x00, &[0; 19]);
+        assert_eq!(Address::from_str(&address), Err(AddressError::InvalidHashLength(19)));
+    }
+}
@@ -1,37 +1,47 @@
 
-use num_bigint::{BigInt, Sign};
+use std::fmt;
 
-pub(crate) struct Base58 ();
+use num_bigint::{BigInt, Sign};
 
+use crate::utils;
 
-impl Base58 {
-    pub fn from_vec_u8(n: Vec<u8>) -> String {
-        // https://appdevtools.com/base58-encoder-decoder
+pub(crate) struct Base58 ();
 
-        let bytes = n.len();
-        let mut remainder = BigInt::from_bytes_be(Sign::Plus, &n[..]);
+/// A decoded Base58Check string whose checksum matched.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Base58Check {
+    pub(crate) version: u8,
+    pub(crate) payload: Vec<u8>,
+}
 
-        let largest = BigInt::from(256).pow(bytes as u32);
-        let mut didgets = 0;
-        let fifty_eight = BigInt::from(58);
-        let mut text = Vec::new();
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Base58Error {
+    /// 0, O, I and l are left out of the alphabet so they can't be mixed up,
+    /// and nothing outside it is allowed either.
+    InvalidCharacter { character: char, position: usize },
+    /// Base58Check needs at least a version byte and the 4 checksum bytes.
+    TooShort(usize),
+    InvalidChecksum,
+}
 
-        while fifty_eight.pow(didgets) < largest {
-            didgets += 1;
-        }
-        didgets -=1;
-        if remainder < BigInt::parse_bytes(b"000AF820000000000000000000000000000000000000000000", 16).unwrap() {
-            didgets -= 1;
+impl fmt::Display for Base58Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidCharacter { character, position } => write!(f, "'{}' at position {} isn't a base58 character", character, position),
+            Self::TooShort(length) => write!(f, "{} bytes is too short for Base58Check", length),
+            Self::InvalidChecksum => write!(f, "the checksum doesn't match"),
         }
+    }
+}
 
-        for i in (0..didgets).rev() {
-            let devisor = remainder.clone() / fifty_eight.pow(i);
-            let result = devisor.to_biguint();
-            text.push(dec_to_base58(result.unwrap().to_bytes_le()[0] as u8));
-            remainder %= fifty_eight.pow(i);
-        }
-        
-        text.into_iter().collect()
+
+impl Base58 {
+    /// Encodes bytes as base58. The bytes are read as one big number, which
+    /// would drop any leading zero bytes, so each of those becomes a '1'.
+    pub fn from_vec_u8(n: Vec<u8>) -> String {
+        // https://appdevtools.com/base58-encoder-decoder
+        let leading_zeros = n.iter().take_while(|byte| **byte == 0).count();
+        "1".repeat(leading_zeros) + &Self::from_bigint(BigInt::from_bytes_be(Sign::Plus, &n))
     }
 
 
@@ -112,6 +122,66 @@ impl Base58 {
     }
 }
 
+impl Base58 {
+    /// Decodes base58 text back into bytes. Each leading '1' stands for a
+    /// leading zero byte, which the number alone would lose.
+    pub fn to_vec_u8(text: &str) -> Result<Vec<u8>, Base58Error> {
+        let fifty_eight = BigInt::from(58);
+        let mut leading_zeros = 0;
+        let mut number = BigInt::from(0);
+        for (position, character) in text.chars().enumerate() {
+            let digit = base58_to_dec(character).ok_or(Base58Error::InvalidCharacter { character, position })?;
+            if digit == 0 && number == BigInt::from(0) {
+                leading_zeros += 1;
+            }
+            number = number * &fifty_eight + digit;
+        }
+
+        let mut bytes = vec![0; leading_zeros];
+        if number > BigInt::from(0) {
+            bytes.extend(number.to_bytes_be().1);
+        }
+        Ok(bytes)
+    }
+}
+
+impl Base58 {
+    /// Base58Check, used by addresses and WIF keys: the version byte, the
+    /// payload, and the first 4 bytes of their double SHA-256 as a checksum.
+    pub fn check_encode(version: u8, payload: &[u8]) -> String {
+        // https://en.bitcoin.it/wiki/Base58Check_encoding
+        let mut bytes = vec![version];
+        bytes.extend(payload);
+        let checksum = utils::double_sha256(&bytes);
+        bytes.extend(&checksum[..4]);
+
+        Self::from_vec_u8(bytes)
+    }
+
+    /// Decodes Base58Check text, checking the checksum.
+    pub fn check_decode(text: &str) -> Result<Base58Check, Base58Error> {
+        let bytes = Self::to_vec_u8(text)?;
+        if bytes.len() < 5 {
+            return Err(Base58Error::TooShort(bytes.len()))
+        }
+
+        let (data, checksum) = bytes.split_at(bytes.len() - 4);
+        if utils::double_sha256(data)[..4] != *checksum {
+            return Err(Base58Error::InvalidChecksum)
+        }
+
+        Ok(Base58Check {
+            version: data[0],
+            payload: data[1..].to_vec(),
+        })
+    }
+}
+
+/// The inverse of `dec_to_base58`, `None` for characters outside the alphabet.
+fn base58_to_dec(c: char) -> Option<u8> {
+    (0..58).find(|n| dec_to_base58(*n) == c)
+}
+
 fn dec_to_base58(n: u8) -> char {
     match n {
         0 => '1',
@@ -178,20 +248,19 @@ fn dec_to_base58(n: u8) -> char {
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
-
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use proptest::prelude::*;
+    use crate::network::Network;
 
     fn hex_string_to_bytes(text: String) -> Vec<u8> {
         let a = text.chars();
         let a = a.collect::<Vec<char>>();
         let a = a.chunks(2);
         let a = a.collect::<Vec<&[char]>>(); 
-        let a = a.iter().map(|&c| c.into_iter().collect::<String>());
+        let a = a.iter().map(|&c| c.iter().collect::<String>());
         let a = a.map(|a| u8::from_str_radix(&a, 16).unwrap());
-        let a = a.collect::<Vec<_>>();
-        a
+        a.collect::<Vec<_>>()
     }
 
     #[test]
@@ -207,7 +276,7 @@ mod tests {
 
     #[test]
     fn test_from_vec_u8() {
-        let n = hex_string_to_bytes("002450ABE3830D8508B69EDF22964582B78CCC45CC55C923A8".to_string());
+        let n = [vec![Network::Mainnet.p2pkh_version()], hex_string_to_bytes("2450ABE3830D8508B69EDF22964582B78CCC45CC55C923A8".to_string())].concat();
 
         let actual = Base58::from_vec_u8(n);
 
@@ -218,7 +287,7 @@ mod tests {
 
     #[test]
     fn test_from_vec_u8_2() {
-        let n = hex_string_to_bytes("000ABDFB3F9CDF20EBC6EC277FCB8186D86D2C3B2095B128E4".to_string());
+        let n = [vec![Network::Mainnet.p2pkh_version()], hex_string_to_bytes("0ABDFB3F9CDF20EBC6EC277FCB8186D86D2C3B2095B128E4".to_string())].concat();
         let actual = Base58::from_vec_u8(n);
 
         let expected = "1yoM4VGhTaE4nCJgbmJoevC6ddoeQUzjM".to_string();
@@ -228,7 +297,7 @@ mod tests {
 
     #[test]
     fn test_from_vec_u8_3() {
-        let n = hex_string_to_bytes("054CF3A338420E93E3C6593A9805B5F0C6EB5D16A73D8BFF08".to_string());
+        let n = [vec![Network::Mainnet.p2sh_version()], hex_string_to_bytes("4CF3A338420E93E3C6593A9805B5F0C6EB5D16A73D8BFF08".to_string())].concat();
         let actual = Base58::from_vec_u8(n);
 
         let expected = "38hu9MkFKM1gBPwYR16zP76ssm8rMrHJ4f".to_string();
@@ -238,7 +307,7 @@ mod tests {
 
     #[test]
     fn test_from_vec_u8_4() {
-        let n = hex_string_to_bytes("6FFCF3A338420E93E3C6593A9805B5F0C6EB5D16A73D8BFF08".to_string());
+        let n = [vec![Network::Testnet.p2pkh_version()], hex_string_to_bytes("FCF3A338420E93E3C6593A9805B5F0C6EB5D16A73D8BFF08".to_string())].concat();
         let actual = Base58::from_vec_u8(n);
 
         let expected = "n4aSSdYfwXhXZCdQiKmJcjTFLTJzLGpyw5".to_string();
@@ -248,13 +317,145 @@ mod tests {
 
     #[test]
     fn test_from_vec_u8_5() {
-        let n = hex_string_to_bytes("6F0CF3A338420E93E3C6593A9805B5F0C6EB5D16A73D8BFF08".to_string());
+        let n = [vec![Network::Testnet.p2pkh_version()], hex_string_to_bytes("0CF3A338420E93E3C6593A9805B5F0C6EB5D16A73D8BFF08".to_string())].concat();
         let actual = Base58::from_vec_u8(n);
 
         let expected = "mghSGbGTuB1qAUwCkcpwfN8pgc8c3NDe1d".to_string();
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_from_vec_u8_leading_zeros() {
+        assert_eq!(Base58::from_vec_u8(vec![]), "");
+        assert_eq!(Base58::from_vec_u8(vec![0]), "1");
+        assert_eq!(Base58::from_vec_u8(vec![0, 0, 0, 1]), "1112");
+        assert_eq!(Base58::from_vec_u8(vec![0; 30]), "1".repeat(30));
+        assert_eq!(Base58::from_vec_u8(vec![0, 0, 0x0a, 0xbd]), "11pQ");
+    }
+
+    #[test]
+    fn test_from_vec_u8_other_lengths() {
+        // a 37 byte WIF private key, Mastering Bitcoin chapter 4
+        let wif = hex_string_to_bytes("803ABA4162C7251C891207B747840551A71939B0DE081F85C4E44CF7C13E41DAA6C609B824".to_string());
+
+        assert_eq!(Base58::from_vec_u8(wif), "5JG9hT3beGTJuUAmCQEmNaxAuMacCTfXuw1R3FCXig23RQHMr4K");
+        assert_eq!(Base58::from_vec_u8(vec![0xff; 38]).len(), 52);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(bytes in proptest::collection::vec(any::<u8>(), 0..=200)) {
+            let encoded = Base58::from_vec_u8(bytes.clone());
+
+            prop_assert_eq!(Base58::to_vec_u8(&encoded), Ok(bytes));
+        }
+
+        #[test]
+        fn prop_one_per_leading_zero(
+            (zeros, rest) in (0usize..=200).prop_flat_map(|zeros| (Just(zeros), proptest::collection::vec(1u8.., 0..=200 - zeros)))
+        ) {
+            let mut bytes = vec![0; zeros];
+            bytes.extend(&rest);
+
+            let encoded = Base58::from_vec_u8(bytes.clone());
+
+            prop_assert_eq!(encoded.chars().take_while(|c| *c == '1').count(), zeros);
+            prop_assert_eq!(&encoded, &("1".repeat(zeros) + &Base58::from_vec_u8(rest)));
+            prop_assert_eq!(Base58::to_vec_u8(&encoded), Ok(bytes));
+        }
+    }
+
+    #[test]
+    fn test_to_vec_u8_round_trip() {
+        let vectors = [
+            ("002450ABE3830D8508B69EDF22964582B78CCC45CC55C923A8", "14K1y4Epb341duzDmWsPniLyBh9EVh8jG3"),
+            ("000ABDFB3F9CDF20EBC6EC277FCB8186D86D2C3B2095B128E4", "1yoM4VGhTaE4nCJgbmJoevC6ddoeQUzjM"),
+            ("054CF3A338420E93E3C6593A9805B5F0C6EB5D16A73D8BFF08", "38hu9MkFKM1gBPwYR16zP76ssm8rMrHJ4f"),
+            ("6FFCF3A338420E93E3C6593A9805B5F0C6EB5D16A73D8BFF08", "n4aSSdYfwXhXZCdQiKmJcjTFLTJzLGpyw5"),
+            ("6F0CF3A338420E93E3C6593A9805B5F0C6EB5D16A73D8BFF08", "mghSGbGTuB1qAUwCkcpwfN8pgc8c3NDe1d"),
+        ];
+
+        for (hex, text) in vectors {
+            let bytes = hex_string_to_bytes(hex.to_string());
+
+            assert_eq!(Base58::to_vec_u8(text), Ok(bytes.clone()), "{}", text);
+            assert_eq!(Base58::to_vec_u8(&Base58::from_vec_u8(bytes.clone())), Ok(bytes));
+        }
+    }
+
+    #[test]
+    fn test_to_vec_u8_from_bigint() {
+        let n = BigInt::parse_bytes(b"01e99423a4ed27608a15a2616a2b0e9e52ced330ac530edcc32c8fc6a526aedd", 16).unwrap();
+
+        let actual = Base58::to_vec_u8(&Base58::from_bigint(n.clone())).unwrap();
+
+        assert_eq!(BigInt::from_bytes_be(Sign::Plus, &actual), n);
+    }
+
+    #[test]
+    fn test_to_vec_u8_leading_ones() {
+        assert_eq!(Base58::to_vec_u8(""), Ok(vec![]));
+        assert_eq!(Base58::to_vec_u8("1"), Ok(vec![0]));
+        assert_eq!(Base58::to_vec_u8("1112"), Ok(vec![0, 0, 0, 1]));
+        assert_eq!(Base58::to_vec_u8("11z"), Ok(vec![0, 0, 57]));
+        assert_eq!(Base58::to_vec_u8("5R"), Ok(vec![1, 0]));
+    }
+
+    #[test]
+    fn test_check_encode() {
+        // https://gobittest.appspot.com/Address
+        let hash = hex_string_to_bytes("2450ABE3830D8508B69EDF22964582B78CCC45CC".to_string());
+
+        assert_eq!(Base58::check_encode(Network::Mainnet.p2pkh_version(), &hash), "14K1y4Epb341duzDmWsPniLyBh9EVh8jG3");
+    }
+
+    #[test]
+    fn test_check_encode_keeps_every_leading_zero() {
+        let encoded = Base58::check_encode(Network::Mainnet.p2pkh_version(), &[0, 0, 1]);
+
+        assert!(encoded.starts_with("111"));
+        assert_eq!(Base58::check_decode(&encoded), Ok(Base58Check { version: Network::Mainnet.p2pkh_version(), payload: vec![0, 0, 1] }));
+    }
+
+    #[test]
+    fn test_check_decode() {
+        let expected = Base58Check {
+            version: Network::Mainnet.p2pkh_version(),
+            payload: hex_string_to_bytes("2450ABE3830D8508B69EDF22964582B78CCC45CC".to_string()),
+        };
+
+        assert_eq!(Base58::check_decode("14K1y4Epb341duzDmWsPniLyBh9EVh8jG3"), Ok(expected));
+    }
+
+    #[test]
+    fn test_check_round_trip() {
+        let versions = [Network::Mainnet, Network::Testnet]
+            .into_iter()
+            .flat_map(|network| [network.p2pkh_version(), network.p2sh_version(), network.wif_version()]);
+        for version in versions {
+            for payload in [vec![], vec![0], vec![0xff; 20], vec![0x42; 33]] {
+                let decoded = Base58::check_decode(&Base58::check_encode(version, &payload));
+
+                assert_eq!(decoded, Ok(Base58Check { version, payload }));
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_decode_rejects_bad_input() {
+        assert_eq!(Base58::check_decode("14K1y4Epb341duzDmWsPniLyBh9EVh8jG4"), Err(Base58Error::InvalidChecksum));
+        assert_eq!(Base58::check_decode("24K1y4Epb341duzDmWsPniLyBh9EVh8jG3"), Err(Base58Error::InvalidChecksum));
+        assert_eq!(Base58::check_decode("1111"), Err(Base58Error::TooShort(4)));
+        assert_eq!(Base58::check_decode("14K1y4Epb341duzDmWsPniLyBh9EVh8jGO"), Err(Base58Error::InvalidCharacter { character: 'O', position: 33 }));
+    }
+
+    #[test]
+    fn test_to_vec_u8_rejects_invalid_characters() {
+        for (text, character, position) in [("0", '0', 0), ("1O1", 'O', 1), ("abcI", 'I', 3), ("l", 'l', 0), ("2 3", ' ', 1), ("1é", 'é', 1)] {
+            assert_eq!(Base58::to_vec_u8(text), Err(Base58Error::InvalidCharacter { character, position }), "{}", text);
+        }
+    }
 }
 
 // 002450ABE3830D8508B69EDF22964582B78CCC45CC55C923A8
@@ -0,0 +1,468 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+// https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+
+/// Each 5 bit value in the data part is written as the character at its index.
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// The checksum is the last 6 characters, one per generator coefficient.
+const CHECKSUM_LENGTH: usize = 6;
+
+const MAX_LENGTH: usize = 90;
+
+/// The coefficients of the BCH generator, and its multiples by 2, 4, 8 and 16.
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+/// x^5 + x^3 + 1, the modulus of the GF(32) the code works over.
+const GF32_MODULUS: u8 = 0b101001;
+
+pub(crate) struct Bech32 ();
+
+/// Which constant the checksum is XORed with. BIP350 added bech32m because
+/// bech32 doesn't notice characters being added or dropped in front of a
+/// final 'p'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Variant {
+    fn constant(&self) -> u32 {
+        match self {
+            Self::Bech32 => 1,
+            Self::Bech32m => 0x2bc830a3,
+        }
+    }
+}
+
+/// A decoded bech32 string whose checksum matched. The data is 5 bit values
+/// with the checksum removed.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Bech32Data {
+    pub(crate) hrp: String,
+    pub(crate) data: Vec<u8>,
+    pub(crate) variant: Variant,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Bech32Error {
+    /// Bech32 strings are at most 90 characters long.
+    TooLong(usize),
+    /// Only US-ASCII 33 to 126 can be in the HRP, and only the 32 characters
+    /// of the charset in the data part.
+    InvalidCharacter { character: char, position: usize },
+    /// The string has to be all lower case or all upper case.
+    MixedCase,
+    /// There was no '1' to split the HRP from the data.
+    MissingSeparator,
+    EmptyHrp,
+    /// The data part needs at least the 6 checksum characters.
+    TooShort(usize),
+    /// Neither the bech32 nor the bech32m checksum matched. The positions are
+    /// of the characters that are most likely typos, when there are one or two
+    /// of them in the data part.
+    InvalidChecksum { typos: Vec<usize> },
+    /// Only 5 bit values can be encoded.
+    InvalidValue(u8),
+    /// Regrouping into bytes left more than 4 bits over, or bits that weren't 0.
+    InvalidPadding,
+}
+
+impl fmt::Display for Bech32Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooLong(length) => write!(f, "{} characters is too long for bech32", length),
+            Self::InvalidCharacter { character, position } => write!(f, "'{}' at position {} isn't allowed", character, position),
+            Self::MixedCase => write!(f, "upper and lower case can't be mixed"),
+            Self::MissingSeparator => write!(f, "the '1' separator is missing"),
+            Self::EmptyHrp => write!(f, "the human readable part is empty"),
+            Self::TooShort(length) => write!(f, "{} characters after the separator is too short for the checksum", length),
+            Self::InvalidChecksum { typos } if typos.is_empty() => write!(f, "the checksum doesn't match"),
+            Self::InvalidChecksum { typos } => write!(f, "the checksum doesn't match, check the characters at positions {:?}", typos),
+            Self::InvalidValue(value) => write!(f, "{} doesn't fit in 5 bits", value),
+            Self::InvalidPadding => write!(f, "the padding is invalid"),
+        }
+    }
+}
+
+impl Bech32 {
+    /// Encodes 5 bit values under the HRP, always in lower case.
+    pub(crate) fn encode(hrp: &str, data: &[u8], variant: Variant) -> Result<String, Bech32Error> {
+        let hrp = hrp.to_lowercase();
+        Self::check_hrp(&hrp)?;
+        if let Some(&value) = data.iter().find(|value| **value > 31) {
+            return Err(Bech32Error::InvalidValue(value))
+        }
+        let length = hrp.len() + 1 + data.len() + CHECKSUM_LENGTH;
+        if length > MAX_LENGTH {
+            return Err(Bech32Error::TooLong(length))
+        }
+
+        let mut values = [expand_hrp(&hrp), data.to_vec(), vec![0; CHECKSUM_LENGTH]].concat();
+        let checksum = polymod(&values) ^ variant.constant();
+        let values_length = values.len();
+        for (i, value) in values[values_length - CHECKSUM_LENGTH..].iter_mut().enumerate() {
+            *value = ((checksum >> (5 * (CHECKSUM_LENGTH - 1 - i))) & 31) as u8;
+        }
+
+        let data_part: String = values[hrp.len() * 2 + 1..].iter().map(|value| CHARSET[*value as usize] as char).collect();
+        Ok(hrp + "1" + &data_part)
+    }
+
+    pub(crate) fn decode(text: &str) -> Result<Bech32Data, Bech32Error> {
+        if let Some((position, character)) = text.chars().enumerate().find(|(_, c)| !(33..=126).contains(&(*c as u32))) {
+            return Err(Bech32Error::InvalidCharacter { character, position })
+        }
+        if text.bytes().any(|b| b.is_ascii_lowercase()) && text.bytes().any(|b| b.is_ascii_uppercase()) {
+            return Err(Bech32Error::MixedCase)
+        }
+        if text.len() > MAX_LENGTH {
+            return Err(Bech32Error::TooLong(text.len()))
+        }
+
+        // The HRP can have a '1' in it, so the separator is the last one.
+        let text = text.to_lowercase();
+        let separator = text.rfind('1').ok_or(Bech32Error::MissingSeparator)?;
+        let (hrp, data_part) = (&text[..separator], &text[separator + 1..]);
+        Self::check_hrp(hrp)?;
+        if data_part.len() < CHECKSUM_LENGTH {
+            return Err(Bech32Error::TooShort(data_part.len()))
+        }
+        let data = data_part.chars().enumerate().map(|(i, character)| {
+            CHARSET.iter().position(|c| *c as char == character).map(|value| value as u8)
+                .ok_or(Bech32Error::InvalidCharacter { character, position: separator + 1 + i })
+        }).collect::<Result<Vec<u8>, Bech32Error>>()?;
+
+        let residue = polymod(&[expand_hrp(hrp), data.clone()].concat());
+        let variant = [Variant::Bech32, Variant::Bech32m].into_iter()
+            .find(|variant| residue == variant.constant())
+            .ok_or_else(|| Bech32Error::InvalidChecksum { typos: locate_typos(residue, separator, data.len()) })?;
+
+        Ok(Bech32Data {
+            hrp: hrp.to_string(),
+            data: data[..data.len() - CHECKSUM_LENGTH].to_vec(),
+            variant,
+        })
+    }
+
+    /// Whether text has the shape of a bech32 string, in one case throughout
+    /// and with only bech32 characters after the separator, whatever its HRP
+    /// and checksum are.
+    pub(crate) fn is_bech32_shaped(text: &str) -> bool {
+        if text.bytes().any(|b| b.is_ascii_lowercase()) && text.bytes().any(|b| b.is_ascii_uppercase()) {
+            return false
+        }
+        match text.to_lowercase().rsplit_once('1') {
+            Some((hrp, data_part)) => !hrp.is_empty()
+                && data_part.len() >= CHECKSUM_LENGTH
+                && data_part.bytes().all(|b| CHARSET.contains(&b)),
+            None => false,
+        }
+    }
+
+    /// Regroups bytes into 5 bit values, padding the last one with zeros.
+    pub(crate) fn to_base32(bytes: &[u8]) -> Vec<u8> {
+        convert_bits(bytes, 8, 5, true).unwrap()
+    }
+
+    /// Regroups 5 bit values into bytes.
+    pub(crate) fn from_base32(values: &[u8]) -> Result<Vec<u8>, Bech32Error> {
+        convert_bits(values, 5, 8, false)
+    }
+
+    /// The HRP is 1 to 83 characters from US-ASCII 33 to 126.
+    fn check_hrp(hrp: &str) -> Result<(), Bech32Error> {
+        if hrp.is_empty() {
+            return Err(Bech32Error::EmptyHrp)
+        }
+        if let Some((position, character)) = hrp.chars().enumerate().find(|(_, c)| !(33..=126).contains(&(*c as u32))) {
+            return Err(Bech32Error::InvalidCharacter { character, position })
+        }
+        if hrp.len() > MAX_LENGTH - 1 - CHECKSUM_LENGTH {
+            return Err(Bech32Error::TooLong(hrp.len() + 1 + CHECKSUM_LENGTH))
+        }
+        Ok(())
+    }
+}
+
+/// The high bits of each HRP character, a 0, then the low bits of each.
+fn expand_hrp(hrp: &str) -> Vec<u8> {
+    hrp.bytes().map(|b| b >> 5).chain([0]).chain(hrp.bytes().map(|b| b & 31)).collect()
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    values.iter().fold(1, |checksum, value| polymod_step(checksum, *value))
+}
+
+/// Multiplies the running remainder by x, adds the value and reduces it
+/// modulo the generator again.
+fn polymod_step(checksum: u32, value: u8) -> u32 {
+    let top = checksum >> 25;
+    let mut checksum = ((checksum & 0x1ffffff) << 5) ^ value as u32;
+    for (i, generator) in GENERATOR.iter().enumerate() {
+        if (top >> i) & 1 == 1 {
+            checksum ^= generator;
+        }
+    }
+    checksum
+}
+
+fn gf32_multiply(a: u8, b: u8) -> u8 {
+    let mut a = a;
+    let mut product = 0;
+    for i in 0..5 {
+        if (b >> i) & 1 == 1 {
+            product ^= a;
+        }
+        a <<= 1;
+        if a & 32 != 0 {
+            a ^= GF32_MODULUS;
+        }
+    }
+    product
+}
+
+/// Multiplies each of the 6 coefficients of a remainder by the same value.
+fn scale_remainder(remainder: u32, factor: u8) -> u32 {
+    (0..CHECKSUM_LENGTH).fold(0, |scaled, i| {
+        let coefficient = ((remainder >> (5 * i)) & 31) as u8;
+        scaled | (gf32_multiply(coefficient, factor) as u32) << (5 * i)
+    })
+}
+
+/// The checksum is linear, so a typo that adds e to the value p places from
+/// the end leaves e * (x^p mod g) in the remainder. Every pattern of up to
+/// two such typos leaves a different remainder in strings up to 90 long, so
+/// matching against them finds the positions. More typos than that can still
+/// happen to match, which is why the positions are only likely ones.
+fn locate_typos(residue: u32, separator: usize, data_length: usize) -> Vec<usize> {
+    // x^p mod g for every position p, counted from the end.
+    let mut powers = vec![1];
+    for _ in 1..data_length {
+        powers.push(polymod_step(*powers.last().unwrap(), 0));
+    }
+    let mut single_typos = HashMap::new();
+    for (position, power) in powers.iter().enumerate() {
+        for error in 1..32 {
+            single_typos.insert(scale_remainder(*power, error), position);
+        }
+    }
+    let to_index = |position: usize| separator + data_length - position;
+
+    let mut best: Vec<usize> = vec![];
+    for variant in [Variant::Bech32, Variant::Bech32m] {
+        let error = residue ^ variant.constant();
+        let typos = match single_typos.get(&error) {
+            Some(&position) => vec![to_index(position)],
+            None => powers.iter().enumerate()
+                .flat_map(|(first, power)| (1..32).map(move |e| (first, scale_remainder(*power, e))))
+                .find_map(|(first, first_error)| match single_typos.get(&(error ^ first_error)) {
+                    Some(&second) if second != first => Some(vec![to_index(first.max(second)), to_index(first.min(second))]),
+                    _ => None,
+                })
+                .unwrap_or_default(),
+        };
+        if best.is_empty() || (!typos.is_empty() && typos.len() < best.len()) {
+            best = typos;
+        }
+    }
+    best
+}
+
+/// Regroups values from one bit width to another, most significant bit first.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, Bech32Error> {
+    let mut accumulator: u32 = 0;
+    let mut bits = 0;
+    let mut converted = vec![];
+    let max_value = (1 << to) - 1;
+    for value in data {
+        if (*value as u32) >> from != 0 {
+            return Err(Bech32Error::InvalidValue(*value))
+        }
+        accumulator = (accumulator << from) | *value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            converted.push(((accumulator >> bits) & max_value) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            converted.push(((accumulator << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || ((accumulator << (to - bits)) & max_value) != 0 {
+        return Err(Bech32Error::InvalidPadding)
+    }
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    #[test]
+    fn test_valid_bech32() {
+        // BIP173
+        let strings = [
+            "A12UEL5L",
+            "a12uel5l",
+            "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+            "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+            "11qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqc8247j",
+            "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+            "?1ezyfcl",
+        ];
+
+        for text in strings {
+            let decoded = Bech32::decode(text).unwrap();
+
+            assert_eq!(decoded.variant, Variant::Bech32);
+            assert_eq!(Bech32::encode(&decoded.hrp, &decoded.data, decoded.variant).unwrap(), text.to_lowercase());
+        }
+    }
+
+    #[test]
+    fn test_valid_bech32m() {
+        // BIP350
+        let strings = [
+            "A1LQFN3A",
+            "a1lqfn3a",
+            "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "11llllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllludsr8",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+            "?1v759aa",
+        ];
+
+        for text in strings {
+            let decoded = Bech32::decode(text).unwrap();
+
+            assert_eq!(decoded.variant, Variant::Bech32m);
+            assert_eq!(Bech32::encode(&decoded.hrp, &decoded.data, decoded.variant).unwrap(), text.to_lowercase());
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let decoded = Bech32::decode("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw").unwrap();
+
+        assert_eq!(decoded.hrp, "abcdef");
+        assert_eq!(decoded.data, (0..32).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_invalid_bech32() {
+        // BIP173
+        let cases = [
+            ("\u{20}1nwldj5", Bech32Error::InvalidCharacter { character: ' ', position: 0 }),
+            ("\u{7f}1axkwrx", Bech32Error::InvalidCharacter { character: '\u{7f}', position: 0 }),
+            ("\u{80}1eym55h", Bech32Error::InvalidCharacter { character: '\u{80}', position: 0 }),
+            ("an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx", Bech32Error::TooLong(91)),
+            ("pzry9x0s0muk", Bech32Error::MissingSeparator),
+            ("1pzry9x0s0muk", Bech32Error::EmptyHrp),
+            ("x1b4n0q5v", Bech32Error::InvalidCharacter { character: 'b', position: 2 }),
+            ("li1dgmt3", Bech32Error::TooShort(5)),
+            ("de1lg7wt\u{ff}", Bech32Error::InvalidCharacter { character: '\u{ff}', position: 8 }),
+            // checksum calculated with the upper case form of the HRP
+            ("A1G7SGD8", Bech32Error::InvalidChecksum { typos: vec![] }),
+            ("10a06t8", Bech32Error::EmptyHrp),
+            ("1qzzfhee", Bech32Error::EmptyHrp),
+        ];
+
+        for (text, error) in cases {
+            assert_eq!(Bech32::decode(text), Err(error), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_invalid_bech32m() {
+        // BIP350
+        let cases = [
+            ("\u{20}1xj0phk", Bech32Error::InvalidCharacter { character: ' ', position: 0 }),
+            ("\u{7f}1g6xzxy", Bech32Error::InvalidCharacter { character: '\u{7f}', position: 0 }),
+            ("\u{80}1vctc34", Bech32Error::InvalidCharacter { character: '\u{80}', position: 0 }),
+            ("an84characterslonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11d6pts4", Bech32Error::TooLong(91)),
+            ("qyrz8wqd2c9m", Bech32Error::MissingSeparator),
+            ("1qyrz8wqd2c9m", Bech32Error::EmptyHrp),
+            ("y1b0jsk6g", Bech32Error::InvalidCharacter { character: 'b', position: 2 }),
+            ("lt1igcx5c0", Bech32Error::InvalidCharacter { character: 'i', position: 3 }),
+            ("in1muywd", Bech32Error::TooShort(5)),
+            ("mm1crxm3i", Bech32Error::InvalidCharacter { character: 'i', position: 8 }),
+            ("au1s5cgom", Bech32Error::InvalidCharacter { character: 'o', position: 7 }),
+            // checksum calculated with the upper case form of the HRP
+            ("M1VUXWEZ", Bech32Error::InvalidChecksum { typos: vec![] }),
+            ("16plkw9", Bech32Error::EmptyHrp),
+            ("1p2gdwpf", Bech32Error::EmptyHrp),
+        ];
+
+        for (text, error) in cases {
+            assert_eq!(Bech32::decode(text), Err(error), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_mixed_case() {
+        assert_eq!(Bech32::decode("A12uEL5L"), Err(Bech32Error::MixedCase));
+        assert_eq!(Bech32::decode("tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47Zagq"), Err(Bech32Error::MixedCase));
+    }
+
+    #[test]
+    fn test_encode_rejects_bad_input() {
+        assert_eq!(Bech32::encode("", &[], Variant::Bech32), Err(Bech32Error::EmptyHrp));
+        assert_eq!(Bech32::encode("a b", &[], Variant::Bech32), Err(Bech32Error::InvalidCharacter { character: ' ', position: 1 }));
+        assert_eq!(Bech32::encode("a", &[32], Variant::Bech32), Err(Bech32Error::InvalidValue(32)));
+        assert_eq!(Bech32::encode("a", &[0; 83], Variant::Bech32m), Err(Bech32Error::TooLong(91)));
+        assert_eq!(Bech32::encode("A", &[], Variant::Bech32m), Ok("a1lqfn3a".to_string()));
+    }
+
+    #[test]
+    fn test_base32() {
+        assert_eq!(Bech32::to_base32(&[0xff]), vec![0x1f, 0x1c]);
+        assert_eq!(Bech32::from_base32(&[0x1f, 0x1c]), Ok(vec![0xff]));
+        assert_eq!(Bech32::to_base32(&[]), vec![]);
+        // 3 bits of padding that aren't all 0
+        assert_eq!(Bech32::from_base32(&[0x1f, 0x1f]), Err(Bech32Error::InvalidPadding));
+        // a whole 5 bit group left over
+        assert_eq!(Bech32::from_base32(&[0x1f]), Err(Bech32Error::InvalidPadding));
+        assert_eq!(Bech32::from_base32(&[32]), Err(Bech32Error::InvalidValue(32)));
+
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(Bech32::from_base32(&Bech32::to_base32(&bytes)), Ok(bytes));
+    }
+
+    fn replace_character(text: &str, position: usize) -> String {
+        let mut bytes = text.as_bytes().to_vec();
+        let value = CHARSET.iter().position(|c| *c == bytes[position]).unwrap();
+        bytes[position] = CHARSET[(value + 7) % 32];
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_locates_one_typo() {
+        let valid = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+        for position in 3..valid.len() {
+            let typo = replace_character(valid, position);
+
+            assert_eq!(Bech32::decode(&typo), Err(Bech32Error::InvalidChecksum { typos: vec![position] }));
+        }
+    }
+
+    #[test]
+    fn test_locates_two_typos() {
+        // bech32m, and longer than the BIP's examples
+        let valid = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+
+        for first in (3..valid.len()).step_by(5) {
+            for second in (first + 1..valid.len()).step_by(3) {
+                let typos = replace_character(&replace_character(valid, first), second);
+
+                assert_eq!(Bech32::decode(&typos), Err(Bech32Error::InvalidChecksum { typos: vec![first, second] }));
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::Debug;
+
+use num_bigint::BigInt;
+
+use crate::field::{Field, FieldElement};
+#[cfg(test)]
+use crate::field::ModP;
+use crate::point::Point;
+use crate::utils;
+
+/// The parameters of a short Weierstrass curve y^2 = x^3 + ax + b over a prime field.
+/// Points are typed by their curve, so points from different curves can't be mixed.
+pub(crate) trait Curve: Clone + Debug + PartialEq + Eq {
+    type Field: Field;
+
+    /// The order of the field the curve is defined over.
+    fn p() -> BigInt {
+        Self::Field::modulus()
+    }
+
+    fn a() -> Self::Field;
+
+    fn b() -> Self::Field;
+
+    /// The order of the generator.
+    fn n() -> BigInt;
+
+    /// The cofactor, the number of points on the curve divided by n.
+    fn h() -> BigInt;
+
+    fn generator() -> Point<Self>;
+}
+
+/// The curve Bitcoin uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Secp256k1;
+
+impl Curve for Secp256k1 {
+    type Field = FieldElement;
+
+    fn a() -> FieldElement {
+        FieldElement::zero()
+    }
+
+    fn b() -> FieldElement {
+        FieldElement::from_u64(7)
+    }
+
+    fn n() -> BigInt {
+        utils::to_bigint(utils::N)
+    }
+
+    fn h() -> BigInt {
+        BigInt::from(1)
+    }
+
+    fn generator() -> Point<Self> {
+        Point::from_xy_unchecked(
+            FieldElement::from_bigint(&BigInt::parse_bytes(b"79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798", 16).unwrap()),
+            FieldElement::from_bigint(&BigInt::parse_bytes(b"483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8", 16).unwrap())
+        )
+    }
+}
+
+/// A toy curve over a small prime field, small enough to check by hand.
+/// ```
+/// // y^2 = x^3 + 7 over F_17 with G = (1, 5), which has order 9 and cofactor 2
+/// type Toy17 = SmallCurve<17, 0, 7, 1, 5, 9, 2>;
+/// ```
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SmallCurve<
+    const P: u64,
+    const A: u64,
+    const B: u64,
+    const GX: u64,
+    const GY: u64,
+    const N: u64,
+    const H: u64,
+>;
+
+#[cfg(test)]
+impl<
+    const P: u64,
+    const A: u64,
+    const B: u64,
+    const GX: u64,
+    const GY: u64,
+    const N: u64,
+    const H: u64,
+> Curve for SmallCurve<P, A, B, GX, GY, N, H> {
+    type Field = ModP<P>;
+
+    fn a() -> ModP<P> {
+        ModP::from_u64(A)
+    }
+
+    fn b() -> ModP<P> {
+        ModP::from_u64(B)
+    }
+
+    fn n() -> BigInt {
+        BigInt::from(N)
+    }
+
+    fn h() -> BigInt {
+        BigInt::from(H)
+    }
+
+    fn generator() -> Point<Self> {
+        Point::from_xy_unchecked(ModP::from_u64(GX), ModP::from_u64(GY))
+    }
+}
@@ -0,0 +1,743 @@
+use std::fmt;
+
+use num_bigint::{BigInt, Sign};
+
+use crate::curve::{Curve, Secp256k1};
+use crate::field::Field;
+use crate::generator_table;
+use crate::point::{self, Point};
+use crate::rfc6979::NonceGenerator;
+use crate::scalar::Scalar;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Signature {
+    pub(crate) r: BigInt,
+    pub(crate) s: BigInt,
+}
+
+/// A signature plus the two bits needed to recover the public key from it:
+/// bit 0 is set when R's y is odd, bit 1 when R's x was n or more and got
+/// reduced when computing r.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RecoverableSignature {
+    pub(crate) signature: Signature,
+    pub(crate) recovery_id: u8,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum SignatureError {
+    /// DER signatures are 8 to 72 bytes, compact ones exactly 64.
+    InvalidLength(usize),
+    /// Recovery ids run from 0 to 3.
+    InvalidRecoveryId(u8),
+    /// DER signatures start with the sequence tag 0x30.
+    ExpectedSequence(u8),
+    /// The sequence claims more bytes than there are.
+    Truncated { declared: usize, available: usize },
+    /// Bytes left over after the sequence, or after the second integer.
+    TrailingBytes(usize),
+    /// r and s must each be tagged as an integer, 0x02.
+    ExpectedInteger { integer: &'static str, found: u8 },
+    ZeroLengthInteger(&'static str),
+    NegativeInteger(&'static str),
+    /// The integer starts with a zero byte it doesn't need.
+    NonMinimalInteger(&'static str),
+    /// r and s have to be between 1 and n - 1.
+    OutOfRange(&'static str),
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidLength(length) => write!(f, "a signature can't be {} bytes long", length),
+            Self::InvalidRecoveryId(id) => write!(f, "recovery id {} isn't between 0 and 3", id),
+            Self::ExpectedSequence(found) => write!(f, "expected the DER sequence tag 0x30 but found {:#04x}", found),
+            Self::Truncated { declared, available } => write!(f, "declared {} bytes but only {} are left", declared, available),
+            Self::TrailingBytes(count) => write!(f, "{} unexpected bytes after the signature", count),
+            Self::ExpectedInteger { integer, found } => write!(f, "expected the DER integer tag 0x02 for {} but found {:#04x}", integer, found),
+            Self::ZeroLengthInteger(integer) => write!(f, "{} has a length of zero", integer),
+            Self::NegativeInteger(integer) => write!(f, "{} is negative", integer),
+            Self::NonMinimalInteger(integer) => write!(f, "{} has an unnecessary leading zero byte", integer),
+            Self::OutOfRange(integer) => write!(f, "{} has to be between 1 and n - 1", integer),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum RecoveryError {
+    InvalidRecoveryId(u8),
+    /// r or s is zero, or n or more.
+    OutOfRange,
+    /// No point on the curve has the x coordinate the recovery id picks.
+    NoPointForR,
+    /// The recovered key would be the point at infinity.
+    Infinity,
+}
+
+impl fmt::Display for RecoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidRecoveryId(id) => write!(f, "recovery id {} isn't between 0 and 3", id),
+            Self::OutOfRange => write!(f, "r and s have to be between 1 and n - 1"),
+            Self::NoPointForR => write!(f, "no point on the curve matches r and the recovery id"),
+            Self::Infinity => write!(f, "the recovered public key is the point at infinity"),
+        }
+    }
+}
+
+impl Signature {
+    /// The DER encoding used in Bitcoin transactions:
+    /// 0x30 [total length] 0x02 [r length] [r] 0x02 [s length] [s]
+    pub(crate) fn to_der(&self) -> Vec<u8> {
+        let mut r = der_integer(&self.r);
+        let mut s = der_integer(&self.s);
+        let mut der = Vec::from([0x30, (r.len() + s.len()) as u8]);
+        der.append(&mut r);
+        der.append(&mut s);
+        der
+    }
+
+    /// Parses a DER signature, enforcing the strict encoding rules of BIP66.
+    pub(crate) fn from_der(der: &[u8]) -> Result<Self, SignatureError> {
+        // https://github.com/bitcoin/bips/blob/master/bip-0066.mediawiki
+        let length = der.len();
+        if !(8..=72).contains(&length) {
+            return Err(SignatureError::InvalidLength(length))
+        }
+        if der[0] != 0x30 {
+            return Err(SignatureError::ExpectedSequence(der[0]))
+        }
+        let declared = der[1] as usize;
+        if declared > length - 2 {
+            return Err(SignatureError::Truncated { declared, available: length - 2 })
+        }
+        if declared < length - 2 {
+            return Err(SignatureError::TrailingBytes(length - 2 - declared))
+        }
+
+        let (r, rest) = parse_der_integer(&der[2..], "r")?;
+        let (s, rest) = parse_der_integer(rest, "s")?;
+        if !rest.is_empty() {
+            return Err(SignatureError::TrailingBytes(rest.len()))
+        }
+
+        Ok(Self { r, s })
+    }
+
+    /// The 64 byte form, r and s as 32 byte big endian numbers.
+    pub(crate) fn to_compact(&self) -> [u8; 64] {
+        let mut compact = [0u8; 64];
+        let (_, r) = self.r.to_bytes_be();
+        let (_, s) = self.s.to_bytes_be();
+        compact[32 - r.len()..32].copy_from_slice(&r);
+        compact[64 - s.len()..].copy_from_slice(&s);
+        compact
+    }
+
+    /// Whether s is in the lower half of the range, as Bitcoin Core's
+    /// standardness rules require.
+    pub(crate) fn is_low_s(&self) -> bool {
+        self.s <= Secp256k1::n() / 2
+    }
+
+    /// (r, s) and (r, n - s) are both valid signatures of the same message,
+    /// so anyone can flip s. Returns the low-S one of the pair.
+    pub(crate) fn normalize_s(&self) -> Self {
+        if self.is_low_s() {
+            return self.clone()
+        }
+        Self { r: self.r.clone(), s: Secp256k1::n() - &self.s }
+    }
+
+    pub(crate) fn from_compact(compact: &[u8]) -> Result<Self, SignatureError> {
+        if compact.len() != 64 {
+            return Err(SignatureError::InvalidLength(compact.len()))
+        }
+        Ok(Self {
+            r: BigInt::from_bytes_be(Sign::Plus, &compact[..32]),
+            s: BigInt::from_bytes_be(Sign::Plus, &compact[32..]),
+        })
+    }
+}
+
+impl RecoverableSignature {
+    /// Pairs a 64 byte compact signature with its recovery id.
+    pub(crate) fn from_compact(compact: &[u8], recovery_id: u8) -> Result<Self, SignatureError> {
+        if recovery_id > 3 {
+            return Err(SignatureError::InvalidRecoveryId(recovery_id))
+        }
+        Ok(Self { signature: Signature::from_compact(compact)?, recovery_id })
+    }
+
+    pub(crate) fn to_compact(&self) -> ([u8; 64], u8) {
+        (self.signature.to_compact(), self.recovery_id)
+    }
+}
+
+/// A DER integer, with a leading zero byte when the top bit would make it negative.
+fn der_integer(value: &BigInt) -> Vec<u8> {
+    let (_, mut bytes) = value.to_bytes_be();
+    if bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0);
+    }
+    let mut integer = Vec::from([0x02, bytes.len() as u8]);
+    integer.append(&mut bytes);
+    integer
+}
+
+/// Reads one tagged integer, returning it and whatever follows.
+fn parse_der_integer<'a>(bytes: &'a [u8], integer: &'static str) -> Result<(BigInt, &'a [u8]), SignatureError> {
+    if bytes.len() < 2 {
+        return Err(SignatureError::Truncated { declared: 2, available: bytes.len() })
+    }
+    if bytes[0] != 0x02 {
+        return Err(SignatureError::ExpectedInteger { integer, found: bytes[0] })
+    }
+    let length = bytes[1] as usize;
+    let rest = &bytes[2..];
+    if length == 0 {
+        return Err(SignatureError::ZeroLengthInteger(integer))
+    }
+    if length > rest.len() {
+        return Err(SignatureError::Truncated { declared: length, available: rest.len() })
+    }
+    let value = &rest[..length];
+    if value[0] & 0x80 != 0 {
+        return Err(SignatureError::NegativeInteger(integer))
+    }
+    if length > 1 && value[0] == 0 && value[1] & 0x80 == 0 {
+        return Err(SignatureError::NonMinimalInteger(integer))
+    }
+    // anything bigger wouldn't fit the 32 bytes of the compact form either
+    let value = BigInt::from_bytes_be(Sign::Plus, value);
+    if value == BigInt::from(0) || value >= Secp256k1::n() {
+        return Err(SignatureError::OutOfRange(integer))
+    }
+    Ok((value, &rest[length..]))
+}
+
+/// Signs the 32 byte hash of a message with an RFC 6979 deterministic nonce,
+/// so the same key and message always give the same signature.
+/// Returns `None` if the private key isn't between 1 and n - 1.
+pub(crate) fn sign(private_key: &BigInt, z: &[u8; 32]) -> Option<Signature> {
+    sign_with_entropy(private_key, z, None)
+}
+
+/// Like `sign`, but mixes extra entropy into the nonce the way libsecp256k1 does.
+pub(crate) fn sign_with_entropy(private_key: &BigInt, z: &[u8; 32], extra_entropy: Option<&[u8; 32]>) -> Option<Signature> {
+    sign_recoverable_with_entropy(private_key, z, extra_entropy).map(|signature| signature.signature)
+}
+
+/// Like `sign`, but also returns the recovery id so the public key can be
+/// recovered with `recover_public_key`.
+pub(crate) fn sign_recoverable(private_key: &BigInt, z: &[u8; 32]) -> Option<RecoverableSignature> {
+    sign_recoverable_with_entropy(private_key, z, None)
+}
+
+fn sign_recoverable_with_entropy(private_key: &BigInt, z: &[u8; 32], extra_entropy: Option<&[u8; 32]>) -> Option<RecoverableSignature> {
+    let n = Secp256k1::n();
+    if !is_valid_private_key(private_key) {
+        return None
+    }
+    // the nonce generator never runs out, so this always finds one
+    NonceGenerator::new(&n, private_key, z, extra_entropy)
+        .find_map(|k| sign_recoverable_with_nonce(private_key, z, &k))
+}
+
+/// Signs the 32 byte hash of a message with the nonce k.
+/// Returns `None` if the private key isn't between 1 and n - 1, or in the
+/// unlikely case that k gives r or s of zero, and a new nonce has to be picked.
+/// Reusing k for two different messages reveals the private key.
+#[cfg(test)]
+pub(crate) fn sign_with_nonce(private_key: &BigInt, z: &[u8; 32], k: &BigInt) -> Option<Signature> {
+    sign_recoverable_with_nonce(private_key, z, k).map(|signature| signature.signature)
+}
+
+/// A key of zero would sign for no public key at all.
+fn is_valid_private_key(private_key: &BigInt) -> bool {
+    *private_key >= BigInt::from(1) && *private_key < Secp256k1::n()
+}
+
+fn sign_recoverable_with_nonce(private_key: &BigInt, z: &[u8; 32], k: &BigInt) -> Option<RecoverableSignature> {
+    // https://en.bitcoin.it/wiki/Elliptic_Curve_Digital_Signature_Algorithm
+    let n = Secp256k1::n();
+    if !is_valid_private_key(private_key) {
+        return None
+    }
+
+    // k is as secret as the private key, so the arithmetic on both is done
+    // with Scalar
+    let k = Scalar::from_bigint(k);
+    let big_r = generator_table::multiply_generator(&k);
+    let x = big_r.x()?.to_bigint();
+    let r = &x % &n;
+    if r == BigInt::from(0) {
+        return None
+    }
+
+    let s = k.invert() * (Scalar::from_bytes_be(z) + Scalar::from_bigint(&r) * Scalar::from_bigint(private_key));
+    if s.is_zero() {
+        return None
+    }
+
+    let signature = Signature { r, s: s.to_bigint() };
+    let mut recovery_id = u8::from(!big_r.y_is_even()) | u8::from(x >= n) << 1;
+    // flipping s to n - s is the same as signing with -k, whose R has the other y
+    if !signature.is_low_s() {
+        recovery_id ^= 1;
+    }
+
+    Some(RecoverableSignature { signature: signature.normalize_s(), recovery_id })
+}
+
+/// Finds the public key that made a signature of a 32 byte message hash.
+pub(crate) fn recover_public_key(z: &[u8; 32], signature: &RecoverableSignature) -> Result<Point<Secp256k1>, RecoveryError> {
+    // https://www.secg.org/sec1-v2.pdf section 4.1.6
+    let n = Secp256k1::n();
+    let RecoverableSignature { signature: Signature { r, s }, recovery_id } = signature;
+    if *recovery_id > 3 {
+        return Err(RecoveryError::InvalidRecoveryId(*recovery_id))
+    }
+    let one = BigInt::from(1);
+    if *r < one || *r >= n || *s < one || *s >= n {
+        return Err(RecoveryError::OutOfRange)
+    }
+
+    let x = if recovery_id & 2 == 0 { r.clone() } else { r + &n };
+    if x >= Secp256k1::p() {
+        return Err(RecoveryError::NoPointForR)
+    }
+    let (_, x_bytes) = x.to_bytes_be();
+    let mut sec1 = vec![0u8; 33];
+    sec1[0] = 2 + (recovery_id & 1);
+    sec1[33 - x_bytes.len()..].copy_from_slice(&x_bytes);
+    let big_r = Point::from_sec1_bytes(&sec1).map_err(|_| RecoveryError::NoPointForR)?;
+
+    // Q = r^-1 (sR - zG)
+    let z = BigInt::from_bytes_be(Sign::Plus, z);
+    let r_inv = point::modulo_inverse(r, &n);
+    let u1 = point::positive_mod(&(-z * &r_inv), &n);
+    let u2 = point::positive_mod(&(s * r_inv), &n);
+    let public_key = Secp256k1::generator()
+        .multiply(&u1)
+        .add(big_r.multiply(&u2));
+
+    if public_key.is_infinity() {
+        return Err(RecoveryError::Infinity)
+    }
+    Ok(public_key)
+}
+
+/// Like `verify`, but also rejects high-S signatures, which are malleable.
+pub(crate) fn verify_low_s(public_key: &Point<Secp256k1>, z: &[u8; 32], signature: &Signature) -> bool {
+    signature.is_low_s() && verify(public_key, z, signature)
+}
+
+/// Checks the signature of a 32 byte message hash against a public key.
+/// Both high-S and low-S signatures are accepted.
+pub(crate) fn verify(public_key: &Point<Secp256k1>, z: &[u8; 32], signature: &Signature) -> bool {
+    let n = Secp256k1::n();
+    let one = BigInt::from(1);
+    if signature.r < one || signature.r >= n || signature.s < one || signature.s >= n {
+        return false
+    }
+    if public_key.is_infinity() {
+        return false
+    }
+    let z = BigInt::from_bytes_be(Sign::Plus, z);
+
+    let s_inv = point::modulo_inverse(&signature.s, &n);
+    let u1 = point::positive_mod(&(z * &s_inv), &n);
+    let u2 = point::positive_mod(&(&signature.r * s_inv), &n);
+    let big_r = Secp256k1::generator()
+        .multiply(&u1)
+        .add(public_key.clone().multiply(&u2));
+
+    match big_r.x() {
+        Some(x) => x.to_bigint() % n == signature.r,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    fn hex_to_bytes(text: &str) -> [u8; 32] {
+        let bytes = BigInt::parse_bytes(text.as_bytes(), 16).unwrap().to_bytes_be().1;
+        let mut padded = [0u8; 32];
+        padded[32 - bytes.len()..].copy_from_slice(&bytes);
+        padded
+    }
+
+    fn hex_to_bigint(text: &str) -> BigInt {
+        BigInt::parse_bytes(text.as_bytes(), 16).unwrap()
+    }
+
+    fn hex_to_vec(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn double_sha256(message: &[u8]) -> [u8; 32] {
+        let once = Sha256::digest(message);
+        Sha256::digest(once).into()
+    }
+
+    #[test]
+    fn test_sign_with_nonce() {
+        // https://github.com/RustCrypto/elliptic-curves/blob/master/k256/src/test_vectors/ecdsa.rs
+        let private_key = hex_to_bigint("ebb2c082fd7727890a28ac82f6bdf97bad8de9f5d7c9028692de1a255cad3e0f");
+        let k = hex_to_bigint("49a0d7b786ec9cde0d0721d72804befd06571c974b191efb42ecf322ba9ddd9a");
+        let z = hex_to_bytes("4b688df40bcedbe641ddb16ff0a1842d9c67ea1c3bf63f3e0471baa664531d1a");
+
+        let actual = sign_with_nonce(&private_key, &z, &k).unwrap();
+
+        let expected = Signature {
+            r: hex_to_bigint("241097efbf8b63bf145c8961dbdf10c310efbb3b2676bbc0f8b08505c9e2f795"),
+            s: hex_to_bigint("021006b7838609339e8b415a7f9acb1b661828131aef1ecbc7955dfb01f3ca0e"),
+        };
+        assert_eq!(actual, expected);
+
+        let public_key = Point::from_xy(
+            &hex_to_bigint("779dd197a5df977ed2cf6cb31d82d43328b790dc6b3b7d4437a427bd5847dfcd"),
+            &hex_to_bigint("e94b724a555b6d017bb7607c3e3281daf5b1699d6ef4124975c9237b917d426f"),
+        ).unwrap();
+        assert!(verify(&public_key, &z, &actual));
+    }
+
+    #[test]
+    fn test_sign_programming_bitcoin() {
+        // Programming Bitcoin chapter 3
+        let private_key = BigInt::from(12345);
+        let z = double_sha256(b"Programming Bitcoin!");
+        let k = BigInt::from(1234567890);
+
+        let actual = sign_with_nonce(&private_key, &z, &k).unwrap();
+
+        let expected = Signature {
+            r: hex_to_bigint("2b698a0f0a4041b77e63488ad48c23e8e8838dd1fb7520408b121697b782ef22"),
+            s: hex_to_bigint("1dbc63bfef4416705e602a7b564161167076d8b20990a0f26f316cff2cb0bc1a"),
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_verify_programming_bitcoin() {
+        // Programming Bitcoin chapter 3
+        let public_key = Point::from_xy(
+            &hex_to_bigint("887387e452b8eacc4acfde10d9aaf7f6d9a0f975aabb10d006e4da568744d06c"),
+            &hex_to_bigint("61de6d95231cd89026e286df3b6ae4a894a3378e393e93a0f45b666329a0ae34"),
+        ).unwrap();
+
+        let z = hex_to_bytes("ec208baa0fc1c19f708a9ca96fdeff3ac3f230bb4a7ba4aede4942ad003c0f60");
+        let signature = Signature {
+            r: hex_to_bigint("ac8d1c87e51d0d441be8b3dd5b05c8795b48875dffe00b7ffcfac23010d3a395"),
+            s: hex_to_bigint("068342ceff8935ededd102dd876ffd6ba72d6a427a3edb13d26eb0781cb423c4"),
+        };
+        assert!(verify(&public_key, &z, &signature));
+
+        let z = hex_to_bytes("7c076ff316692a3d7eb3c3bb0f8b1488cf72e1afcd929e29307032997a838a3d");
+        let signature = Signature {
+            r: hex_to_bigint("00eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c"),
+            s: hex_to_bigint("c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab6"),
+        };
+        assert!(verify(&public_key, &z, &signature));
+    }
+
+    #[test]
+    fn test_sign_is_deterministic() {
+        // the RFC 6979 nonce for this key and message is checked in rfc6979.rs
+        let private_key = BigInt::from(1);
+        let z: [u8; 32] = Sha256::digest(b"Satoshi Nakamoto").into();
+        let k = hex_to_bigint("8F8A276C19F4149656B280621E358CCE24F5F52542772691EE69063B74F15D15");
+
+        let actual = sign(&private_key, &z).unwrap();
+
+        assert_eq!(actual, sign(&private_key, &z).unwrap());
+        assert_eq!(Some(actual), sign_with_nonce(&private_key, &z, &k));
+    }
+
+    #[test]
+    fn test_sign_with_entropy() {
+        let private_key = BigInt::from(1);
+        let public_key = Secp256k1::generator();
+        let z: [u8; 32] = Sha256::digest(b"Satoshi Nakamoto").into();
+
+        let plain = sign(&private_key, &z).unwrap();
+        let with_entropy = sign_with_entropy(&private_key, &z, Some(&[7; 32])).unwrap();
+
+        assert_ne!(plain, with_entropy);
+        assert!(verify(&public_key, &z, &with_entropy));
+    }
+
+    #[test]
+    fn test_verify_rejects_wrong_message() {
+        let private_key = BigInt::from(12345);
+        let public_key = Secp256k1::generator().multiply(&private_key);
+        let signature = sign(&private_key, &double_sha256(b"Programming Bitcoin!")).unwrap();
+
+        assert!(verify(&public_key, &double_sha256(b"Programming Bitcoin!"), &signature));
+        assert!(!verify(&public_key, &double_sha256(b"Programming Bitcoin?"), &signature));
+    }
+
+    #[test]
+    fn test_sign_rejects_out_of_range_keys() {
+        let z = double_sha256(b"Programming Bitcoin!");
+
+        for private_key in [BigInt::from(0), BigInt::from(-1), Secp256k1::n()] {
+            assert_eq!(sign(&private_key, &z), None);
+            assert_eq!(sign_recoverable(&private_key, &z), None);
+            assert_eq!(sign_with_nonce(&private_key, &z, &BigInt::from(1234567890)), None);
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_out_of_range() {
+        let private_key = BigInt::from(12345);
+        let public_key = Secp256k1::generator().multiply(&private_key);
+        let z = double_sha256(b"Programming Bitcoin!");
+        let signature = sign(&private_key, &z).unwrap();
+
+        let zero_r = Signature { r: BigInt::from(0), s: signature.s.clone() };
+        let big_s = Signature { r: signature.r.clone(), s: &signature.s + Secp256k1::n() };
+
+        assert!(!verify(&public_key, &z, &zero_r));
+        assert!(!verify(&public_key, &z, &big_s));
+        assert!(!verify(&Point::Infinity, &z, &signature));
+    }
+
+    fn programming_bitcoin_signature() -> (Signature, Vec<u8>) {
+        // Programming Bitcoin chapter 4
+        let signature = Signature {
+            r: hex_to_bigint("37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6"),
+            s: hex_to_bigint("8ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec"),
+        };
+        let der = hex_to_vec("3045022037206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c60221008ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec");
+        (signature, der)
+    }
+
+    #[test]
+    fn test_to_der() {
+        let (signature, expected) = programming_bitcoin_signature();
+
+        assert_eq!(signature.to_der(), expected);
+    }
+
+    #[test]
+    fn test_from_der() {
+        let (expected, der) = programming_bitcoin_signature();
+
+        assert_eq!(Signature::from_der(&der), Ok(expected));
+    }
+
+    #[test]
+    fn test_der_round_trip_short_integers() {
+        let signature = Signature { r: BigInt::from(1), s: BigInt::from(0x80) };
+
+        let der = signature.to_der();
+
+        assert_eq!(der, hex_to_vec("300702010102020080"));
+        assert_eq!(Signature::from_der(&der), Ok(signature));
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        let (signature, _) = programming_bitcoin_signature();
+
+        let compact = signature.to_compact();
+
+        assert_eq!(&compact[..32], &hex_to_vec("37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6")[..]);
+        assert_eq!(Signature::from_compact(&compact), Ok(signature));
+        assert_eq!(Signature::from_compact(&compact[1..]), Err(SignatureError::InvalidLength(63)));
+    }
+
+    #[test]
+    fn test_from_der_rejects_bad_lengths() {
+        let (_, der) = programming_bitcoin_signature();
+
+        let mut trailing = der.clone();
+        trailing.push(0x01);
+        let mut short_sequence = der.clone();
+        short_sequence[1] -= 1;
+        let mut long_sequence = der.clone();
+        long_sequence[1] += 1;
+
+        assert_eq!(Signature::from_der(&der[..7]), Err(SignatureError::InvalidLength(7)));
+        assert_eq!(Signature::from_der(&trailing), Err(SignatureError::TrailingBytes(1)));
+        assert_eq!(Signature::from_der(&short_sequence), Err(SignatureError::TrailingBytes(1)));
+        assert_eq!(Signature::from_der(&long_sequence), Err(SignatureError::Truncated { declared: 70, available: 69 }));
+    }
+
+    #[test]
+    fn test_from_der_rejects_trailing_bytes_inside_sequence() {
+        // the sequence length covers an extra byte after s
+        let der = hex_to_vec("300702010102010100");
+
+        assert_eq!(Signature::from_der(&der), Err(SignatureError::TrailingBytes(1)));
+    }
+
+    #[test]
+    fn test_from_der_rejects_bad_tags() {
+        let (_, der) = programming_bitcoin_signature();
+
+        let mut not_sequence = der.clone();
+        not_sequence[0] = 0x31;
+        let mut r_not_integer = der.clone();
+        r_not_integer[2] = 0x03;
+        let mut s_not_integer = der.clone();
+        s_not_integer[36] = 0x03;
+
+        assert_eq!(Signature::from_der(&not_sequence), Err(SignatureError::ExpectedSequence(0x31)));
+        assert_eq!(Signature::from_der(&r_not_integer), Err(SignatureError::ExpectedInteger { integer: "r", found: 0x03 }));
+        assert_eq!(Signature::from_der(&s_not_integer), Err(SignatureError::ExpectedInteger { integer: "s", found: 0x03 }));
+    }
+
+    #[test]
+    fn test_from_der_rejects_non_strict_integers() {
+        let negative_r = hex_to_vec("3006020181020101");
+        let padded_r = hex_to_vec("300702020001020101");
+        let empty_s = hex_to_vec("3006020101020001");
+        let negative_s = hex_to_vec("3006020101020181");
+        let padded_s = hex_to_vec("300702010102020001");
+
+        assert_eq!(Signature::from_der(&negative_r), Err(SignatureError::NegativeInteger("r")));
+        assert_eq!(Signature::from_der(&padded_r), Err(SignatureError::NonMinimalInteger("r")));
+        assert_eq!(Signature::from_der(&empty_s), Err(SignatureError::ZeroLengthInteger("s")));
+        assert_eq!(Signature::from_der(&negative_s), Err(SignatureError::NegativeInteger("s")));
+        assert_eq!(Signature::from_der(&padded_s), Err(SignatureError::NonMinimalInteger("s")));
+    }
+
+    #[test]
+    fn test_from_der_rejects_out_of_range_integers() {
+        let zero_r = hex_to_vec("3006020100020101");
+        let n = Secp256k1::n().to_bytes_be().1;
+        let n_s = [&hex_to_vec("3026020101022100")[..], &n].concat();
+        // 0x01 followed by 32 bytes, which doesn't fit the compact form
+        let long_r = [&hex_to_vec("3026022101")[..], &[0xff; 32], &hex_to_vec("020101")].concat();
+
+        assert_eq!(Signature::from_der(&zero_r), Err(SignatureError::OutOfRange("r")));
+        assert_eq!(Signature::from_der(&n_s), Err(SignatureError::OutOfRange("s")));
+        assert_eq!(Signature::from_der(&long_r), Err(SignatureError::OutOfRange("r")));
+    }
+
+    #[test]
+    fn test_signature_error_messages() {
+        assert_eq!(SignatureError::NegativeInteger("s").to_string(), "s is negative");
+        assert_eq!(SignatureError::ExpectedSequence(0x31).to_string(), "expected the DER sequence tag 0x30 but found 0x31");
+    }
+
+    #[test]
+    fn test_sign_produces_low_s() {
+        let private_key = BigInt::from(12345);
+        let public_key = Secp256k1::generator().multiply(&private_key);
+
+        for i in 0..16 {
+            let z = double_sha256(&[i]);
+            let signature = sign(&private_key, &z).unwrap();
+
+            assert!(signature.is_low_s());
+            assert!(verify_low_s(&public_key, &z, &signature));
+        }
+    }
+
+    #[test]
+    fn test_normalize_s() {
+        let n = Secp256k1::n();
+        let low = Signature { r: BigInt::from(1), s: &n / 2 };
+        let high = Signature { r: BigInt::from(1), s: &n / 2 + 1 };
+
+        assert!(low.is_low_s());
+        assert!(!high.is_low_s());
+        assert_eq!(low.normalize_s(), low);
+        assert_eq!(high.normalize_s(), Signature { r: BigInt::from(1), s: &n - (&n / 2 + 1) });
+        assert!(high.normalize_s().is_low_s());
+    }
+
+    #[test]
+    fn test_verify_low_s_rejects_high_s() {
+        // the second Programming Bitcoin chapter 3 signature has a high s
+        let public_key = Point::from_xy(
+            &hex_to_bigint("887387e452b8eacc4acfde10d9aaf7f6d9a0f975aabb10d006e4da568744d06c"),
+            &hex_to_bigint("61de6d95231cd89026e286df3b6ae4a894a3378e393e93a0f45b666329a0ae34"),
+        ).unwrap();
+        let z = hex_to_bytes("7c076ff316692a3d7eb3c3bb0f8b1488cf72e1afcd929e29307032997a838a3d");
+        let high = Signature {
+            r: hex_to_bigint("00eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c"),
+            s: hex_to_bigint("c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab6"),
+        };
+
+        assert!(verify(&public_key, &z, &high));
+        assert!(!verify_low_s(&public_key, &z, &high));
+
+        let low = high.normalize_s();
+        assert!(verify(&public_key, &z, &low));
+        assert!(verify_low_s(&public_key, &z, &low));
+    }
+
+    #[test]
+    fn test_recover_public_key() {
+        // https://github.com/RustCrypto/elliptic-curves/blob/master/k256/src/ecdsa.rs
+        let z: [u8; 32] = Sha256::digest(b"example message").into();
+        let vectors = [
+            (
+                "021a7a569e91dbf60581509c7fc946d1003b60c7dee85299538db6353538d59574",
+                "ce53abb3721bafc561408ce8ff99c909f7f0b18a2f788649d6470162ab1aa0323971edc523a6d6453f3fb6128d318d9db1a5ff3386feb1047d9816e780039d52",
+                0,
+            ),
+            (
+                "036d6caac248af96f6afa7f904f550253a0f3ef3f5aa2fe6838a95b216691468e2",
+                "46c05b6368a44b8810d79859441d819b8e7cdc8bfd371e35c53196f4bcacdb5135c7facce2a97b95eacba8a586d87b7958aaf8368ab29cee481f76e871dbd9cb",
+                1,
+            ),
+        ];
+
+        for (public_key, compact, recovery_id) in vectors {
+            let signature = RecoverableSignature::from_compact(&hex_to_vec(compact), recovery_id).unwrap();
+
+            let actual = recover_public_key(&z, &signature).unwrap();
+
+            assert_eq!(actual.to_sec1_bytes(true), hex_to_vec(public_key));
+        }
+    }
+
+    #[test]
+    fn test_sign_recoverable_round_trip() {
+        for i in 1..16u32 {
+            let private_key = BigInt::from(i) * BigInt::from(0x1234_5678_9abc_def1u64);
+            let public_key = Secp256k1::generator().multiply(&private_key);
+            let z = double_sha256(&i.to_be_bytes());
+
+            let signature = sign_recoverable(&private_key, &z).unwrap();
+
+            assert_eq!(signature.signature, sign(&private_key, &z).unwrap());
+            assert_eq!(recover_public_key(&z, &signature), Ok(public_key.clone()));
+
+            let flipped = RecoverableSignature { recovery_id: signature.recovery_id ^ 1, ..signature };
+            assert_ne!(recover_public_key(&z, &flipped), Ok(public_key));
+        }
+    }
+
+    #[test]
+    fn test_recover_public_key_rejects_bad_input() {
+        let z = double_sha256(b"Programming Bitcoin!");
+        let signature = sign_recoverable(&BigInt::from(12345), &z).unwrap();
+
+        let bad_id = RecoverableSignature { recovery_id: 4, ..signature.clone() };
+        let zero_r = RecoverableSignature {
+            signature: Signature { r: BigInt::from(0), s: signature.signature.s.clone() },
+            ..signature.clone()
+        };
+        // r + n is bigger than p for any r this large
+        let no_point = RecoverableSignature { recovery_id: signature.recovery_id | 2, ..signature };
+
+        assert_eq!(recover_public_key(&z, &bad_id), Err(RecoveryError::InvalidRecoveryId(4)));
+        assert_eq!(recover_public_key(&z, &zero_r), Err(RecoveryError::OutOfRange));
+        assert_eq!(recover_public_key(&z, &no_point), Err(RecoveryError::NoPointForR));
+        assert_eq!(RecoverableSignature::from_compact(&[0u8; 64], 4), Err(SignatureError::InvalidRecoveryId(4)));
+    }
+}
@@ -0,0 +1,553 @@
+use std::fmt::Debug;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use num_bigint::{BigInt, Sign};
+
+/// Arithmetic over a prime field, as needed by `Point`.
+/// Every value is kept fully reduced so `==` compares field elements.
+pub(crate) trait Field:
+    Copy
+    + Debug
+    + Eq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Neg<Output = Self>
+{
+    fn zero() -> Self;
+
+    fn one() -> Self;
+
+    /// The prime the field is taken modulo.
+    fn modulus() -> BigInt;
+
+    fn from_u64(n: u64) -> Self;
+
+    /// Reduces an arbitrary integer into the field.
+    fn from_bigint(n: &BigInt) -> Self;
+
+    fn to_bigint(&self) -> BigInt;
+
+    /// Big endian bytes, padded to the width of the modulus.
+    fn to_bytes_be(&self) -> Vec<u8>;
+
+    fn square(&self) -> Self {
+        *self * *self
+    }
+
+    /// The multiplicative inverse. Zero has no inverse and maps to zero.
+    fn invert(&self) -> Self;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::zero()
+    }
+
+    fn is_even(&self) -> bool;
+
+    /// Picks `b` when `choice` is set and `a` otherwise, without branching on `choice`.
+    fn conditional_select(a: &Self, b: &Self, choice: bool) -> Self;
+}
+
+#[cfg(test)]
+thread_local! {
+    static OPERATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// How many additions, subtractions and multiplications this thread has done
+/// on `FieldElement`s, for checking that secret dependent code does a fixed
+/// amount of work.
+#[cfg(test)]
+pub(crate) fn operation_count() -> usize {
+    OPERATIONS.with(|count| count.get())
+}
+
+#[cfg(test)]
+fn count_operation() {
+    OPERATIONS.with(|count| count.set(count.get() + 1));
+}
+
+// p = 2^256 - 2^32 - 2^9 - 2^8 - 2^7 - 2^6 - 2^4 - 1
+const P: [u64; 4] = [
+    0xFFFFFFFEFFFFFC2F,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+/// 2^256 - p, so 2^256 ≡ C (mod p)
+const C: u64 = 0x1000003D1;
+
+/// p - 2, the exponent used for inversion by Fermat's little theorem
+const P_MINUS_2: [u64; 4] = [
+    0xFFFFFFFEFFFFFC2D,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+/// (p + 1) / 4, since p ≡ 3 (mod 4) a square root is a^((p + 1) / 4)
+const P_PLUS_1_OVER_4: [u64; 4] = [
+    0xFFFFFFFFBFFFFF0C,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0x3FFFFFFFFFFFFFFF,
+];
+
+/// An element of the secp256k1 base field, stored as four little endian
+/// 64 bit limbs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FieldElement([u64; 4]);
+
+impl FieldElement {
+    /// Parses 32 big endian bytes, returning `None` if the value is not below p.
+    pub(crate) fn from_bytes_be(bytes: &[u8; 32]) -> Option<Self> {
+        let mut limbs = [0u64; 4];
+        for (i, chunk) in bytes.rchunks(8).enumerate() {
+            limbs[i] = u64::from_be_bytes(chunk.try_into().unwrap());
+        }
+        let (_, borrow) = sub_limbs(&limbs, &P);
+        if borrow {
+            Some(Self(limbs))
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, chunk) in bytes.rchunks_mut(8).enumerate() {
+            chunk.copy_from_slice(&self.0[i].to_be_bytes());
+        }
+        bytes
+    }
+
+    /// The square root whose square is self, or `None` if self isn't a square.
+    /// The other root is its negation.
+    pub(crate) fn sqrt(&self) -> Option<Self> {
+        let root = self.pow(&P_PLUS_1_OVER_4);
+        if root.square() == *self {
+            Some(root)
+        } else {
+            None
+        }
+    }
+
+    /// Raises self to a power given as little endian limbs.
+    fn pow(&self, exponent: &[u64; 4]) -> Self {
+        let mut result = Self::one();
+        for limb in exponent.iter().rev() {
+            for bit in (0..64).rev() {
+                result = result.square();
+                if (limb >> bit) & 1 == 1 {
+                    result = result * *self;
+                }
+            }
+        }
+        result
+    }
+
+    /// Brings a value below 2^256 into the range [0, p).
+    fn reduce_once(limbs: [u64; 4]) -> Self {
+        // limbs >= p exactly when limbs + C overflows 256 bits
+        let (sum, carry) = add_limbs(&limbs, &[C, 0, 0, 0]);
+        Self(select(carry, &sum, &limbs))
+    }
+
+    /// Reduces a 512 bit product modulo p.
+    fn reduce_wide(t: [u64; 8]) -> Self {
+        // fold the high half down using 2^256 ≡ C
+        let mut r = [0u64; 4];
+        let mut carry = 0u128;
+        for i in 0..4 {
+            let v = t[i] as u128 + t[i + 4] as u128 * C as u128 + carry;
+            r[i] = v as u64;
+            carry = v >> 64;
+        }
+
+        // what is left over is at most 34 bits, fold it again
+        let mut extra = carry * C as u128;
+        for limb in r.iter_mut() {
+            let v = *limb as u128 + (extra as u64) as u128;
+            *limb = v as u64;
+            extra = (extra >> 64) + (v >> 64);
+        }
+
+        // a final carry out of 2^256 wraps around as one more C. It only
+        // happens when r wrapped round to below 2^67, so adding C can't carry
+        // out again.
+        let (wrapped, overflow) = add_limbs(&r, &[C * extra as u64, 0, 0, 0]);
+        debug_assert!(!overflow);
+        Self::reduce_once(wrapped)
+    }
+}
+
+impl Field for FieldElement {
+    fn zero() -> Self {
+        Self([0; 4])
+    }
+
+    fn one() -> Self {
+        Self([1, 0, 0, 0])
+    }
+
+    fn modulus() -> BigInt {
+        Self(P).to_bigint()
+    }
+
+    fn from_u64(n: u64) -> Self {
+        Self([n, 0, 0, 0])
+    }
+
+    fn from_bigint(n: &BigInt) -> Self {
+        let p = Self::modulus();
+        let (_, bytes) = (((n % &p) + &p) % &p).to_bytes_be();
+        let mut padded = [0u8; 32];
+        padded[32 - bytes.len()..].copy_from_slice(&bytes);
+        Self::from_bytes_be(&padded).unwrap()
+    }
+
+    fn to_bigint(&self) -> BigInt {
+        BigInt::from_bytes_be(Sign::Plus, &self.to_bytes())
+    }
+
+    fn to_bytes_be(&self) -> Vec<u8> {
+        self.to_bytes().to_vec()
+    }
+
+    fn invert(&self) -> Self {
+        self.pow(&P_MINUS_2)
+    }
+
+    fn is_even(&self) -> bool {
+        self.0[0] & 1 == 0
+    }
+
+    fn conditional_select(a: &Self, b: &Self, choice: bool) -> Self {
+        Self(select(choice, &b.0, &a.0))
+    }
+}
+
+impl Add for FieldElement {
+    type Output = Self;
+
+    // the | merges the two carries without branching on them
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, rhs: Self) -> Self {
+        #[cfg(test)]
+        count_operation();
+        let (sum, carry) = add_limbs(&self.0, &rhs.0);
+        let (reduced, reduced_carry) = add_limbs(&sum, &[C, 0, 0, 0]);
+        Self(select(carry | reduced_carry, &reduced, &sum))
+    }
+}
+
+impl Sub for FieldElement {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        #[cfg(test)]
+        count_operation();
+        let (difference, borrow) = sub_limbs(&self.0, &rhs.0);
+        // on underflow add p back, which is the same as taking C away
+        let (corrected, _) = sub_limbs(&difference, &[C, 0, 0, 0]);
+        Self(select(borrow, &corrected, &difference))
+    }
+}
+
+impl Mul for FieldElement {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        #[cfg(test)]
+        count_operation();
+        let mut t = [0u64; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let v = t[i + j] as u128 + self.0[i] as u128 * rhs.0[j] as u128 + carry;
+                t[i + j] = v as u64;
+                carry = v >> 64;
+            }
+            t[i + 4] = carry as u64;
+        }
+        Self::reduce_wide(t)
+    }
+}
+
+impl Neg for FieldElement {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::zero() - self
+    }
+}
+
+pub(crate) fn add_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut out = [0u64; 4];
+    let mut carry = false;
+    for i in 0..4 {
+        let (v, c1) = a[i].overflowing_add(b[i]);
+        let (v, c2) = v.overflowing_add(carry as u64);
+        out[i] = v;
+        carry = c1 | c2;
+    }
+    (out, carry)
+}
+
+pub(crate) fn sub_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut out = [0u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (v, b1) = a[i].overflowing_sub(b[i]);
+        let (v, b2) = v.overflowing_sub(borrow as u64);
+        out[i] = v;
+        borrow = b1 | b2;
+    }
+    (out, borrow)
+}
+
+/// Picks `a` when `choice` is set and `b` otherwise, without branching.
+pub(crate) fn select(choice: bool, a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mask = (choice as u64).wrapping_neg();
+    let mut out = [0u64; 4];
+    for i in 0..4 {
+        out[i] = (a[i] & mask) | (b[i] & !mask);
+    }
+    out
+}
+
+/// An element of the small prime field of order `P`.
+/// Only meant for the toy curves used to check the point arithmetic by hand.
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ModP<const P: u64>(u64);
+
+#[cfg(test)]
+impl<const P: u64> Field for ModP<P> {
+    fn zero() -> Self {
+        Self(0)
+    }
+
+    fn one() -> Self {
+        Self(1 % P)
+    }
+
+    fn modulus() -> BigInt {
+        BigInt::from(P)
+    }
+
+    fn from_u64(n: u64) -> Self {
+        Self(n % P)
+    }
+
+    fn from_bigint(n: &BigInt) -> Self {
+        let p = BigInt::from(P);
+        let (_, digits) = (((n % &p) + &p) % &p).to_u64_digits();
+        Self(digits.first().copied().unwrap_or(0))
+    }
+
+    fn to_bigint(&self) -> BigInt {
+        BigInt::from(self.0)
+    }
+
+    fn to_bytes_be(&self) -> Vec<u8> {
+        let width = (64 - P.leading_zeros()).div_ceil(8) as usize;
+        self.0.to_be_bytes()[8 - width..].to_vec()
+    }
+
+    fn invert(&self) -> Self {
+        // Fermat's little theorem, P is prime
+        let mut result = Self::one();
+        let mut base = *self;
+        let mut exponent = P - 2;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+        result
+    }
+
+    fn is_even(&self) -> bool {
+        self.0.is_multiple_of(2)
+    }
+
+    fn conditional_select(a: &Self, b: &Self, choice: bool) -> Self {
+        let mask = (choice as u64).wrapping_neg();
+        Self((b.0 & mask) | (a.0 & !mask))
+    }
+}
+
+#[cfg(test)]
+impl<const P: u64> Add for ModP<P> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(((self.0 as u128 + rhs.0 as u128) % P as u128) as u64)
+    }
+}
+
+#[cfg(test)]
+impl<const P: u64> Sub for ModP<P> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(((self.0 as u128 + P as u128 - rhs.0 as u128) % P as u128) as u64)
+    }
+}
+
+#[cfg(test)]
+impl<const P: u64> Mul for ModP<P> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self(((self.0 as u128 * rhs.0 as u128) % P as u128) as u64)
+    }
+}
+
+#[cfg(test)]
+impl<const P: u64> Neg for ModP<P> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::zero() - self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use proptest::prelude::*;
+
+    fn p() -> BigInt {
+        let two = BigInt::from(2);
+        two.pow(256)
+            - two.pow(32)
+            - two.pow(9)
+            - two.pow(8)
+            - two.pow(7)
+            - two.pow(6)
+            - two.pow(4)
+            - BigInt::from(1u8)
+    }
+
+    fn test_values() -> Vec<BigInt> {
+        vec![
+            BigInt::from(0),
+            BigInt::from(1),
+            BigInt::from(7),
+            p() - 1,
+            p() - 2,
+            BigInt::parse_bytes(b"79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798", 16).unwrap(),
+            BigInt::parse_bytes(b"483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8", 16).unwrap(),
+            BigInt::parse_bytes(b"FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2E", 16).unwrap(),
+            BigInt::parse_bytes(b"00000001000003D1000000000000000000000000000000000000000000000000", 16).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_field_element_arithmetic_matches_bigint() {
+        let p = p();
+        for a in test_values() {
+            for b in test_values() {
+                let fa = FieldElement::from_bigint(&a);
+                let fb = FieldElement::from_bigint(&b);
+
+                assert_eq!((fa + fb).to_bigint(), (&a + &b) % &p);
+                assert_eq!((fa - fb).to_bigint(), ((&a - &b) % &p + &p) % &p);
+                assert_eq!((fa * fb).to_bigint(), (&a * &b) % &p);
+            }
+        }
+    }
+
+    /// Anything below p, with extra weight on the values just below p and
+    /// the small ones, where the carries and the wrap around are.
+    fn field_value() -> impl Strategy<Value = BigInt> {
+        prop_oneof![
+            any::<[u8; 32]>().prop_map(|bytes| BigInt::from_bytes_be(Sign::Plus, &bytes) % p()),
+            any::<u64>().prop_map(|k| p() - 1 - k),
+            any::<u64>().prop_map(BigInt::from),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn prop_field_element_arithmetic_matches_bigint(a in field_value(), b in field_value()) {
+            let p = p();
+            let fa = FieldElement::from_bigint(&a);
+            let fb = FieldElement::from_bigint(&b);
+
+            prop_assert_eq!((fa + fb).to_bigint(), (&a + &b) % &p);
+            prop_assert_eq!((fa - fb).to_bigint(), ((&a - &b) % &p + &p) % &p);
+            prop_assert_eq!((fa * fb).to_bigint(), (&a * &b) % &p);
+        }
+
+        #[test]
+        fn prop_field_element_invert_matches_bigint(a in field_value().prop_filter("zero has no inverse", |a| *a != BigInt::from(0))) {
+            let p = p();
+            let fa = FieldElement::from_bigint(&a);
+
+            prop_assert_eq!(fa.invert().to_bigint(), a.modpow(&(&p - 2), &p));
+        }
+    }
+
+    #[test]
+    fn test_field_element_square() {
+        for a in test_values() {
+            let fa = FieldElement::from_bigint(&a);
+            assert_eq!(fa.square(), fa * fa);
+        }
+    }
+
+    #[test]
+    fn test_field_element_invert() {
+        for a in test_values().into_iter().skip(1) {
+            let fa = FieldElement::from_bigint(&a);
+            assert_eq!(fa * fa.invert(), FieldElement::one());
+        }
+    }
+
+    #[test]
+    fn test_field_element_from_bigint_reduces() {
+        let actual = FieldElement::from_bigint(&(p() + 5));
+        assert_eq!(actual, FieldElement::from_u64(5));
+
+        let actual = FieldElement::from_bigint(&BigInt::from(-1));
+        assert_eq!(actual.to_bigint(), p() - 1);
+    }
+
+    #[test]
+    fn test_field_element_rejects_p() {
+        let bytes = FieldElement::from_bigint(&(p() - 1)).to_bytes();
+        let mut too_big = bytes;
+        too_big[31] += 1;
+        assert_eq!(FieldElement::from_bytes_be(&bytes).map(|f| f.to_bigint()), Some(p() - 1));
+        assert_eq!(FieldElement::from_bytes_be(&too_big), None);
+    }
+
+    #[test]
+    fn test_field_element_sqrt() {
+        for a in test_values() {
+            let square = FieldElement::from_bigint(&a).square();
+            let root = square.sqrt().unwrap();
+            assert_eq!(root.square(), square);
+        }
+    }
+
+    #[test]
+    fn test_field_element_sqrt_of_non_square() {
+        // 5^3 + 7 has no square root, so there is no point with x = 5
+        let actual = FieldElement::from_u64(132).sqrt();
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn test_mod_p_invert() {
+        let actual = ModP::<19>::from_u64(7).invert();
+        let expected = ModP::<19>::from_u64(11);
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::OnceLock;
+
+use crate::curve::{Curve, Secp256k1};
+use crate::point::{Point, ProjectivePoint};
+use crate::scalar::Scalar;
+
+/// Bits of the scalar handled by each window.
+const WINDOW_BITS: usize = 4;
+
+const WINDOW_SIZE: usize = 1 << WINDOW_BITS;
+
+const WINDOWS: usize = 256 / WINDOW_BITS;
+
+/// `TABLE[i][j]` is j * 16^i * G.
+type Table = Vec<[ProjectivePoint<Secp256k1>; WINDOW_SIZE]>;
+
+static TABLE: OnceLock<Table> = OnceLock::new();
+
+fn table() -> &'static Table {
+    TABLE.get_or_init(|| {
+        let mut base = ProjectivePoint::from_affine(&Secp256k1::generator());
+        let mut table = Vec::with_capacity(WINDOWS);
+        for _ in 0..WINDOWS {
+            let mut row: [ProjectivePoint<Secp256k1>; WINDOW_SIZE] = std::array::from_fn(|_| ProjectivePoint::infinity());
+            for j in 1..WINDOW_SIZE {
+                row[j] = row[j - 1].add(&base);
+            }
+            // normalizing keeps the table small numbers and makes the next base 16 times this one
+            for entry in row.iter_mut() {
+                *entry = ProjectivePoint::from_affine(&entry.to_affine());
+            }
+            base = row[WINDOW_SIZE - 1].add(&base);
+            table.push(row);
+        }
+        table
+    })
+}
+
+/// Multiplies the secp256k1 generator by a secret scalar using a table of
+/// multiples of G that is built the first time it is needed.
+/// Each 4 bit window of the scalar picks one table entry, reading every entry
+/// in the row so the memory access doesn't depend on the scalar, and the
+/// picks are summed with complete additions. No doublings are needed, which
+/// makes this much faster than a Montgomery ladder.
+pub(crate) fn multiply_generator(k: &Scalar) -> Point<Secp256k1> {
+    let scalar = k.to_bytes();
+
+    let table = table();
+    let mut total = ProjectivePoint::infinity();
+    for (i, row) in table.iter().enumerate() {
+        // the windows go from the least significant end, the last byte
+        let window = (scalar[31 - i / 2] >> (WINDOW_BITS * (i % 2))) & 0x0F;
+        let mut entry = ProjectivePoint::infinity();
+        for (j, candidate) in row.iter().enumerate() {
+            entry = ProjectivePoint::conditional_select(&entry, candidate, j as u8 == window);
+        }
+        total = total.add(&entry);
+    }
+    total.to_affine()
+}
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use num_bigint::BigInt;
+    use crate::field;
+
+    fn test_scalars() -> Vec<BigInt> {
+        let n = Secp256k1::n();
+        vec![
+            BigInt::from(0),
+            BigInt::from(1),
+            BigInt::from(2),
+            BigInt::from(15),
+            BigInt::from(16),
+            BigInt::from(12345),
+            BigInt::parse_bytes(b"038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14776", 16).unwrap(),
+            BigInt::parse_bytes(b"FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF", 16).unwrap(),
+            &n - 1,
+            n,
+        ]
+    }
+
+    #[test]
+    fn test_multiply_generator_matches_multiply() {
+        for k in test_scalars() {
+            let expected = Secp256k1::generator().multiply(&k);
+            let actual = multiply_generator(&Scalar::from_bigint(&k));
+            assert_eq!(actual, expected, "k = {}", k);
+        }
+    }
+
+    #[test]
+    fn test_multiply_generator_cost_ignores_hamming_weight() {
+        // build the table first so it isn't counted
+        multiply_generator(&Scalar::one());
+
+        // 0 and n give infinity, which skips the final inversion, but neither is a valid secret
+        let operations = test_scalars()[1..9].iter().map(|k| {
+            let k = Scalar::from_bigint(k);
+            let before = field::operation_count();
+            multiply_generator(&k);
+            field::operation_count() - before
+        }).collect::<Vec<_>>();
+
+        assert!(operations.iter().all(|count| *count == operations[0]), "{:?}", operations);
+    }
+
+    /// Compares the table against the other ways of computing k * G.
+    /// Run with `cargo test --release -- --ignored --nocapture bench`
+    #[test]
+    #[ignore]
+    fn bench_multiply_generator() {
+        let k = BigInt::parse_bytes(b"038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14776", 16).unwrap();
+        let scalar = Scalar::from_bigint(&k);
+        let rounds = 200;
+
+        let start = std::time::Instant::now();
+        multiply_generator(&scalar);
+        println!("building the table:       {:?}", start.elapsed());
+
+        let start = std::time::Instant::now();
+        for _ in 0..rounds {
+            Secp256k1::generator().multiply(&k);
+        }
+        println!("multiply:                 {:?} per call", start.elapsed() / rounds);
+
+        let start = std::time::Instant::now();
+        for _ in 0..rounds {
+            multiply_generator(&scalar);
+        }
+        println!("multiply_generator:       {:?} per call", start.elapsed() / rounds);
+    }
+}
@@ -1,15 +1,178 @@
-#![feature(const_option)]
-
 // P 82ish
 
 // use primitive_types::U512;
 
+use std::env;
+use std::process;
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256};
+
+use address::Address;
+use ecdsa::Signature;
+use point::Point;
+use schnorr::{SchnorrSignature, XOnlyPublicKey};
+use wallet::Wallet;
+
 // mod ch4;
+mod curve;
+mod ecdsa;
+mod field;
+mod scalar;
+mod generator_table;
+mod message;
+mod network;
 mod point;
+mod rfc6979;
+mod schnorr;
 mod utils;
 mod wallet;
 mod base58;
+mod bech32;
+mod address;
+
+const USAGE: &str = "usage:
+    mastering_bitcoin <address, WIF or hex private key>...
+    mastering_bitcoin sign <WIF> <message>
+    mastering_bitcoin verify <hex public key> <hex signature> <message>
+    mastering_bitcoin verifybatch (<hex x-only public key> <hex signature> <message>)...
+    mastering_bitcoin signmessage <WIF> <message>
+    mastering_bitcoin verifymessage <address> <base64 signature> <message>";
 
 fn main() {
     // ch4::ch4();
+
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let result = match arguments.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => Err(USAGE.to_string()),
+        ["sign", wif, message] => sign(wif, message),
+        ["verify", public_key, signature, message] => verify(public_key, signature, message),
+        ["verifybatch", ref batch @ ..] if !batch.is_empty() && batch.len().is_multiple_of(3) => verify_batch(batch),
+        ["signmessage", wif, message] => sign_message(wif, message),
+        ["verifymessage", address, signature, message] => verify_message(address, signature, message),
+        ["sign" | "verify" | "verifybatch" | "signmessage" | "verifymessage", ..] => Err(USAGE.to_string()),
+        // Describes each address, WIF private key or hex private key given on
+        // the command line.
+        _ => {
+            for argument in &arguments {
+                println!("{}: {}", argument, describe(argument));
+            }
+            Ok(())
+        }
+    };
+
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+/// Signs the SHA-256 of the message with ECDSA, and the message itself with
+/// BIP340, which hashes it as part of signing.
+fn sign(wif: &str, message: &str) -> Result<(), String> {
+    let wallet = Wallet::from_wif(wif).map_err(|error| error.to_string())?;
+    let z: [u8; 32] = Sha256::digest(message.as_bytes()).into();
+
+    println!("ECDSA {}", utils::to_hex_string(&wallet.sign(&z).to_der()));
+    println!("BIP340 {}", utils::to_hex_string(&wallet.sign_schnorr(message.as_bytes(), &rand::random()).to_bytes()));
+    Ok(())
+}
+
+/// Checks an ECDSA signature for a 33 or 65 byte SEC public key, or a BIP340
+/// one for a 32 byte x-only key.
+fn verify(public_key: &str, signature: &str, message: &str) -> Result<(), String> {
+    let public_key = utils::from_hex_string(public_key).ok_or("the public key isn't hex")?;
+    let signature = utils::from_hex_string(signature).ok_or("the signature isn't hex")?;
+
+    let result = if let Ok(public_key) = <[u8; 32]>::try_from(&public_key[..]) {
+        let (public_key, signature) = parse_schnorr(&public_key, &signature)?;
+        if schnorr::verify(&public_key, message.as_bytes(), &signature) { "valid" } else { "invalid" }
+    } else {
+        let public_key = Point::from_sec1_bytes(&public_key).map_err(|error| error.to_string())?;
+        let signature = Signature::from_der(&signature).map_err(|error| error.to_string())?;
+        let z: [u8; 32] = Sha256::digest(message.as_bytes()).into();
+        match (ecdsa::verify_low_s(&public_key, &z, &signature), ecdsa::verify(&public_key, &z, &signature)) {
+            (true, _) => "valid",
+            (false, true) => "valid, but with a high S that nodes won't relay",
+            (false, false) => "invalid",
+        }
+    };
+    println!("{}", result);
+    Ok(())
+}
+
+/// Checks BIP340 signatures given as key, signature and message triples all
+/// at once.
+fn verify_batch(arguments: &[&str]) -> Result<(), String> {
+    let mut parsed = Vec::new();
+    for triple in arguments.chunks(3) {
+        let public_key = utils::from_hex_string(triple[0]).ok_or("a public key isn't hex")?;
+        let public_key: [u8; 32] = public_key.try_into().map_err(|_| "x-only public keys are 32 bytes")?;
+        let signature = utils::from_hex_string(triple[1]).ok_or("a signature isn't hex")?;
+        parsed.push(parse_schnorr(&public_key, &signature)?);
+    }
+    let batch: Vec<_> = parsed
+        .iter()
+        .zip(arguments.chunks(3))
+        .map(|((public_key, signature), triple)| (public_key, triple[2].as_bytes(), signature))
+        .collect();
+
+    match schnorr::verify_batch(&batch) {
+        Ok(()) => println!("all {} valid", batch.len()),
+        Err(error) => println!("{}", error),
+    }
+    Ok(())
+}
+
+fn parse_schnorr(public_key: &[u8; 32], signature: &[u8]) -> Result<(XOnlyPublicKey, SchnorrSignature), String> {
+    let public_key = XOnlyPublicKey::from_bytes(public_key).map_err(|error| error.to_string())?;
+    let signature: &[u8; 64] = signature.try_into().map_err(|_| "BIP340 signatures are 64 bytes")?;
+    Ok((public_key, SchnorrSignature::from_bytes(signature)))
+}
+
+fn sign_message(wif: &str, message: &str) -> Result<(), String> {
+    let wallet = Wallet::from_wif(wif).map_err(|error| error.to_string())?;
+    println!("{} {}", wallet.address(), wallet.sign_message(message));
+    Ok(())
+}
+
+fn verify_message(address: &str, signature: &str, message: &str) -> Result<(), String> {
+    let valid = message::verify_message(address, signature, message).map_err(|error| error.to_string())?;
+    println!("{}", if valid { "valid" } else { "invalid" });
+    Ok(())
+}
+
+fn describe(argument: &str) -> String {
+    let error = match Address::from_str(argument) {
+        Ok(address) => return describe_address(&address),
+        Err(error) => error,
+    };
+    if let Ok(wallet) = Wallet::from_wif(argument) {
+        return describe_wallet(&wallet)
+    }
+    if argument.len() == 64 {
+        if let Ok(wallet) = Wallet::try_from(argument.to_string()) {
+            return format!("WIF {}, {}", wallet.wif_encoded_private_key(), describe_wallet(&wallet))
+        }
+    }
+    error.to_string()
+}
+
+fn describe_address(address: &Address) -> String {
+    let detail = match (address.witness_version(), address.witness_program()) {
+        (Some(version), Some(program)) => format!("witness version {}, program {}", version, utils::to_hex_string(program)),
+        _ => format!("hash {}", utils::to_hex_string(address.hash().unwrap())),
+    };
+    format!("{} on {:?}, {}", address.address_type(), address.network(), detail)
+}
+
+fn describe_wallet(wallet: &Wallet) -> String {
+    format!(
+        "{:?} private key {} ({}), public key {} (uncompressed {}, x-only {}), P2PKH {}, P2WPKH {}",
+        wallet.network(), wallet.private_key(), wallet.decoded_private_key(),
+        wallet.pub_key_compressed_string(), wallet.pub_key_uncompressed_string(),
+        utils::to_hex_string(&wallet.pub_key_x_only_bytes()),
+        wallet.address(),
+        wallet.p2wpkh_address().unwrap_or_else(|error| format!("none, {}", error))
+    )
 }
@@ -0,0 +1,198 @@
+use std::fmt;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use num_bigint::BigInt;
+
+use crate::base58::{Base58, Base58Check};
+use crate::ecdsa::{self, RecoverableSignature};
+use crate::network::Network;
+use crate::utils;
+
+// https://en.bitcoin.it/wiki/Message_signing
+// The legacy scheme behind Bitcoin Core's signmessage and verifymessage RPCs.
+
+/// 0x18 is the length of "Bitcoin Signed Message:\n".
+const MESSAGE_MAGIC: &[u8] = b"\x18Bitcoin Signed Message:\n";
+
+/// The header byte is 27 + recovery id, plus 4 if the key is compressed.
+const HEADER_BASE: u8 = 27;
+const HEADER_COMPRESSED: u8 = 4;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum MessageError {
+    /// Not valid Base58Check, or not 21 bytes once decoded.
+    InvalidAddress,
+    /// A valid address, but not P2PKH, so there's no key to check against.
+    AddressNoKey(u8),
+    /// Not base64, or not 65 bytes once decoded.
+    MalformedSignature,
+    /// The header byte has to be between 27 and 34.
+    InvalidHeader(u8),
+    /// No public key could be recovered from the signature.
+    PublicKeyNotRecovered,
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidAddress => write!(f, "invalid address"),
+            Self::AddressNoKey(version) => write!(f, "address version {:#04x} doesn't refer to a key", version),
+            Self::MalformedSignature => write!(f, "malformed base64 signature"),
+            Self::InvalidHeader(header) => write!(f, "signature header {} isn't between 27 and 34", header),
+            Self::PublicKeyNotRecovered => write!(f, "no public key could be recovered from the signature"),
+        }
+    }
+}
+
+/// The hash that actually gets signed: the magic prefix, the message length
+/// as a varint and the message, hashed twice with SHA-256.
+pub(crate) fn message_hash(message: &str) -> [u8; 32] {
+    let mut preimage = MESSAGE_MAGIC.to_vec();
+    preimage.extend(varint(message.len() as u64));
+    preimage.extend(message.as_bytes());
+    utils::double_sha256(&preimage)
+}
+
+/// Signs a message the way Bitcoin Core's signmessage does, returning the
+/// 65 byte recoverable signature as base64.
+/// `compressed` says which form of the public key the address was made from.
+/// Returns `None` if the private key isn't between 1 and n - 1.
+pub(crate) fn sign_message(private_key: &BigInt, compressed: bool, message: &str) -> Option<String> {
+    let signature = ecdsa::sign_recoverable(private_key, &message_hash(message))?;
+    let (compact, recovery_id) = signature.to_compact();
+
+    let mut header = HEADER_BASE + recovery_id;
+    if compressed {
+        header += HEADER_COMPRESSED;
+    }
+
+    let mut bytes = vec![header];
+    bytes.extend(compact);
+    Some(STANDARD.encode(bytes))
+}
+
+/// Checks a base64 signature against a P2PKH address, the way Bitcoin Core's
+/// verifymessage does. Returns `Ok(false)` if the signature is well formed
+/// but was made by some other key or for some other message.
+pub(crate) fn verify_message(address: &str, signature: &str, message: &str) -> Result<bool, MessageError> {
+    let key_hash = p2pkh_key_hash(address)?;
+
+    let bytes = STANDARD.decode(signature).map_err(|_| MessageError::MalformedSignature)?;
+    if bytes.len() != 65 {
+        return Err(MessageError::MalformedSignature)
+    }
+    let header = bytes[0];
+    if !(HEADER_BASE..HEADER_BASE + 8).contains(&header) {
+        return Err(MessageError::InvalidHeader(header))
+    }
+    let recovery_id = (header - HEADER_BASE) & 3;
+    let compressed = header - HEADER_BASE >= HEADER_COMPRESSED;
+
+    let signature = RecoverableSignature::from_compact(&bytes[1..], recovery_id)
+        .map_err(|_| MessageError::MalformedSignature)?;
+    let public_key = ecdsa::recover_public_key(&message_hash(message), &signature)
+        .map_err(|_| MessageError::PublicKeyNotRecovered)?;
+
+    Ok(utils::hash160(&public_key.to_sec1_bytes(compressed)) == key_hash)
+}
+
+/// Pulls the 20 byte public key hash out of a mainnet or testnet P2PKH address.
+fn p2pkh_key_hash(address: &str) -> Result<[u8; 20], MessageError> {
+    let Base58Check { version, payload } = Base58::check_decode(address).map_err(|_| MessageError::InvalidAddress)?;
+    let key_hash = payload.try_into().map_err(|_| MessageError::InvalidAddress)?;
+    match Network::from_p2pkh_version(version) {
+        Some(_) => Ok(key_hash),
+        None => Err(MessageError::AddressNoKey(version)),
+    }
+}
+
+/// Bitcoin's variable length integer, the CompactSize in Bitcoin Core.
+fn varint(n: u64) -> Vec<u8> {
+    match n {
+        0..=0xfc => vec![n as u8],
+        0xfd..=0xffff => [&[0xfd], &(n as u16).to_le_bytes()[..]].concat(),
+        0x10000..=0xffff_ffff => [&[0xfe], &(n as u32).to_le_bytes()[..]].concat(),
+        _ => [&[0xff], &n.to_le_bytes()[..]].concat(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::curve::{Curve, Secp256k1};
+
+    // https://github.com/bitcoin/bitcoin/blob/master/test/functional/rpc_signmessage.py
+    // cUeKHd5orzT3mz8P9pxyREHfsWtVfgsfDjiZZBcjUBAaGk1BTj7N, a compressed testnet key
+    const CORE_PRIVATE_KEY: &[u8] = b"d2b8a0116d641fe7d3036f8464628fb595b480414c13a301b3d4038c811c28b0";
+    const CORE_ADDRESS: &str = "mpLQjfK79b7CCV4VMJWEWAj5Mpx8Up5zxB";
+    const CORE_MESSAGE: &str = "This is just a test message";
+    const CORE_SIGNATURE: &str = "INbVnW4e6PeRmsv2Qgu8NuopvrVjkcxob+sX8OcZG0SALhWybUjzMLPdAsXI46YZGb0KQTRii+wWIQzRpG/U+S0=";
+
+    fn p2pkh_address(public_key: &[u8], version: u8) -> String {
+        Base58::check_encode(version, &utils::hash160(public_key))
+    }
+
+    #[test]
+    fn test_varint() {
+        assert_eq!(varint(0), vec![0]);
+        assert_eq!(varint(0xfc), vec![0xfc]);
+        assert_eq!(varint(0xfd), vec![0xfd, 0xfd, 0x00]);
+        assert_eq!(varint(0x1234), vec![0xfd, 0x34, 0x12]);
+        assert_eq!(varint(0x10000), vec![0xfe, 0x00, 0x00, 0x01, 0x00]);
+        assert_eq!(varint(0x1_0000_0000), vec![0xff, 0, 0, 0, 0, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_sign_message_matches_bitcoin_core() {
+        let private_key = BigInt::parse_bytes(CORE_PRIVATE_KEY, 16).unwrap();
+
+        let signature = sign_message(&private_key, true, CORE_MESSAGE).unwrap();
+
+        assert_eq!(signature, CORE_SIGNATURE);
+    }
+
+    #[test]
+    fn test_verify_message_from_bitcoin_core() {
+        assert_eq!(verify_message(CORE_ADDRESS, CORE_SIGNATURE, CORE_MESSAGE), Ok(true));
+        assert_eq!(verify_message(CORE_ADDRESS, CORE_SIGNATURE, "This is just a test message."), Ok(false));
+    }
+
+    #[test]
+    fn test_sign_and_verify_message_uncompressed() {
+        let private_key = BigInt::from(0x1234_5678u64);
+        let public_key = Secp256k1::generator().multiply(&private_key);
+        let uncompressed_address = p2pkh_address(&public_key.to_sec1_bytes(false), Network::Mainnet.p2pkh_version());
+        let compressed_address = p2pkh_address(&public_key.to_sec1_bytes(true), Network::Mainnet.p2pkh_version());
+
+        let signature = sign_message(&private_key, false, "Mastering Bitcoin").unwrap();
+
+        assert_eq!(verify_message(&uncompressed_address, &signature, "Mastering Bitcoin"), Ok(true));
+        // the header says uncompressed, so the compressed key's address doesn't match
+        assert_eq!(verify_message(&compressed_address, &signature, "Mastering Bitcoin"), Ok(false));
+    }
+
+    #[test]
+    fn test_sign_message_rejects_out_of_range_keys() {
+        assert_eq!(sign_message(&BigInt::from(0), true, CORE_MESSAGE), None);
+    }
+
+    #[test]
+    fn test_verify_message_rejects_malformed_input() {
+        let mut bad_checksum = CORE_ADDRESS.to_string();
+        bad_checksum.replace_range(33.., "C");
+        let p2sh = p2pkh_address(&[2; 33], Network::Mainnet.p2sh_version());
+        let mut bad_header = STANDARD.decode(CORE_SIGNATURE).unwrap();
+        bad_header[0] = 35;
+
+        assert_eq!(verify_message(&bad_checksum, CORE_SIGNATURE, CORE_MESSAGE), Err(MessageError::InvalidAddress));
+        assert_eq!(verify_message(&p2sh, CORE_SIGNATURE, CORE_MESSAGE), Err(MessageError::AddressNoKey(0x05)));
+        assert_eq!(verify_message(CORE_ADDRESS, "not base64!", CORE_MESSAGE), Err(MessageError::MalformedSignature));
+        assert_eq!(verify_message(CORE_ADDRESS, &CORE_SIGNATURE[4..], CORE_MESSAGE), Err(MessageError::MalformedSignature));
+        assert_eq!(
+            verify_message(CORE_ADDRESS, &STANDARD.encode(bad_header), CORE_MESSAGE),
+            Err(MessageError::InvalidHeader(35)),
+        );
+    }
+}
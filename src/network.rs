@@ -0,0 +1,113 @@
+// https://en.bitcoin.it/wiki/List_of_address_prefixes
+// https://github.com/bitcoin/bitcoin/blob/master/src/kernel/chainparams.cpp
+
+/// The chain a key or address belongs to. Each one has its own version bytes
+/// and HRP so that coins can't be sent to an address on the wrong chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Network {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    /// Testnet comes before signet and regtest, so when they share a version
+    /// byte or HRP the lookups below answer testnet.
+    pub(crate) const ALL: [Network; 4] = [Self::Mainnet, Self::Testnet, Self::Signet, Self::Regtest];
+
+    /// The version byte at the start of a P2PKH address.
+    pub(crate) fn p2pkh_version(&self) -> u8 {
+        match self {
+            Self::Mainnet => 0x00,
+            Self::Testnet | Self::Signet | Self::Regtest => 0x6f,
+        }
+    }
+
+    /// The version byte at the start of a P2SH address.
+    pub(crate) fn p2sh_version(&self) -> u8 {
+        match self {
+            Self::Mainnet => 0x05,
+            Self::Testnet | Self::Signet | Self::Regtest => 0xc4,
+        }
+    }
+
+    /// The version byte at the start of a WIF private key.
+    pub(crate) fn wif_version(&self) -> u8 {
+        match self {
+            Self::Mainnet => 0x80,
+            Self::Testnet | Self::Signet | Self::Regtest => 0xef,
+        }
+    }
+
+    /// The 4 version bytes of a BIP32 extended public key, which make it start
+    /// with "xpub" or "tpub".
+    // kept with the other version bytes, though there are no extended keys yet
+    #[allow(dead_code)]
+    pub(crate) fn xpub_version(&self) -> [u8; 4] {
+        match self {
+            Self::Mainnet => [0x04, 0x88, 0xb2, 0x1e],
+            Self::Testnet | Self::Signet | Self::Regtest => [0x04, 0x35, 0x87, 0xcf],
+        }
+    }
+
+    /// The human readable part in front of a segwit address.
+    pub(crate) fn bech32_hrp(&self) -> &'static str {
+        match self {
+            Self::Mainnet => "bc",
+            Self::Testnet | Self::Signet => "tb",
+            Self::Regtest => "bcrt",
+        }
+    }
+
+    pub(crate) fn from_p2pkh_version(version: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|network| network.p2pkh_version() == version)
+    }
+
+    pub(crate) fn from_p2sh_version(version: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|network| network.p2sh_version() == version)
+    }
+
+    pub(crate) fn from_wif_version(version: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|network| network.wif_version() == version)
+    }
+
+    /// Expects the HRP in lower case.
+    pub(crate) fn from_bech32_hrp(hrp: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|network| network.bech32_hrp() == hrp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use crate::base58::Base58;
+    use crate::utils;
+
+    #[test]
+    fn test_lookups_prefer_testnet() {
+        assert_eq!(Network::from_p2pkh_version(0x00), Some(Network::Mainnet));
+        assert_eq!(Network::from_p2pkh_version(0x6f), Some(Network::Testnet));
+        assert_eq!(Network::from_p2sh_version(0xc4), Some(Network::Testnet));
+        assert_eq!(Network::from_wif_version(0xef), Some(Network::Testnet));
+        assert_eq!(Network::from_wif_version(0x00), None);
+        assert_eq!(Network::from_bech32_hrp("tb"), Some(Network::Testnet));
+        assert_eq!(Network::from_bech32_hrp("bcrt"), Some(Network::Regtest));
+        assert_eq!(Network::from_bech32_hrp("BC"), None);
+    }
+
+    #[test]
+    fn test_xpub_prefix() {
+        for (network, prefix) in [(Network::Mainnet, "xpub"), (Network::Testnet, "tpub"), (Network::Regtest, "tpub")] {
+            for fill in [0x00, 0xff] {
+                // version, depth, parent fingerprint, child number, chain code and key
+                let payload = [&network.xpub_version()[..], &[fill; 74]].concat();
+                let checksum = utils::double_sha256(&payload);
+                let xpub = Base58::from_vec_u8([&payload[..], &checksum[..4]].concat());
+
+                assert!(xpub.starts_with(prefix), "{}", xpub);
+            }
+        }
+    }
+}
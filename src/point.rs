@@ -1,115 +1,452 @@
 use std::cmp::Eq;
-use std::ops::{Add, Mul};
+use std::fmt;
 
-use num_bigint::{BigInt, BigUint, ToBigInt};
+use num_bigint::{BigInt, BigUint};
 
-use crate::utils;
+use crate::curve::{Curve, Secp256k1};
+use crate::field::{Field, FieldElement};
 
 #[derive(Debug, PartialEq, Eq)]
-pub(crate) struct Point {
-    x: BigInt,
-    y: BigInt,
+pub(crate) enum PointError {
+    /// SEC1 public keys are 33 bytes compressed or 65 bytes uncompressed.
+    InvalidLength(usize),
+    /// The first byte isn't 02 or 03 for a 33 byte key, or 04 for a 65 byte one.
+    InvalidPrefix(u8),
+    /// A coordinate is not below p.
+    CoordinateOutOfRange,
+    /// The coordinates don't satisfy the curve equation, or no y exists for x.
+    NotOnCurve,
+    /// The point at infinity, which has no coordinates and is never a valid key.
+    Infinity,
 }
 
-impl Point {
-    pub(crate) fn add(self, other: Self, p: &BigInt, a: &BigInt) -> Self {
-        // let this = &self;
-        // let other = &other;
-
-        // let zero = BigInt::from(0);
-        // if self.x < zero {
-        //     return other
-        // }
-        // if other.x < zero {
-        //     return self
-        // }
-        let s = if self.x == other.x {
-            if self.y != other.y {
-                return Self {
-                    x: BigInt::from(-1),
-                    y: BigInt::from(-1),
-                }
-            }
-            let dy = positive_mod(&(3 * self.x.pow(2) + a), &p);
-            let dx = modulo_inverse(&(BigInt::from(2) * self.y()), &p);
-            dy * dx
-        } else {
-            positive_mod(&((other.y() - self.y()) * modulo_inverse(&(other.x() - self.x()), &p)), &p)
-        };
-        let x = positive_mod(&(s.pow(2) - (self.x() + other.x())), &p);
-        let y = p - positive_mod(&(self.y() + s * (x.clone() - self.x())), &p);
-
-        Self {
-            x,
-            y,
+impl fmt::Display for PointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidLength(length) => write!(f, "a public key is 33 or 65 bytes, not {}", length),
+            Self::InvalidPrefix(prefix) => write!(f, "a public key can't start with {:#04x}", prefix),
+            Self::CoordinateOutOfRange => write!(f, "a coordinate isn't below p"),
+            Self::NotOnCurve => write!(f, "the point isn't on the curve"),
+            Self::Infinity => write!(f, "the point at infinity isn't a public key"),
         }
     }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Point<C: Curve> {
+    /// The point at infinity, the identity of the group.
+    Infinity,
+    Affine {
+        x: C::Field,
+        y: C::Field,
+    },
+}
 
-    pub(crate) fn multiply(self, rhs: &BigInt, p: &BigInt, a: &BigInt) -> Self {
-        let powers = powers_of_two(rhs);
+impl<C: Curve> Point<C> {
+    pub(crate) fn add(self, other: Self) -> Self {
+        JacobianPoint::from_affine(&self)
+            .add(&JacobianPoint::from_affine(&other))
+            .to_affine()
+    }
+
+    /// Multiplies by a public scalar. Like the other scalar multiplications,
+    /// it reduces the scalar mod the number of points first, so negative ones
+    /// work too.
+    pub(crate) fn multiply(self, rhs: &BigInt) -> Self {
+        let powers = powers_of_two(&positive_mod(rhs, &(C::n() * C::h())));
+        if powers.is_empty() {
+            return Self::Infinity
+        }
         let power = powers[0];
         let mut last_power = power;
-        let mut point = self.double_n_times(power, &p, &a);
+        let mut point = JacobianPoint::from_affine(&self).double_n_times(power);
         let mut point_total = point.clone();
         for power in &powers[1..] {
-            point = point.clone().double_n_times(power - last_power, &p, &a);
-            point_total = point_total.add(point.clone(), &p, &a);
+            point = point.double_n_times(power - last_power);
+            point_total = point_total.add(&point);
             last_power = *power;
         }
-        point_total
+        point_total.to_affine()
     }
 
-    pub(crate) fn from_xy(x: BigInt, y: BigInt) -> Self {
-        Self {
+    /// Builds a point from its affine coordinates, checking that both are in
+    /// the range [0, p) and that the point is on the curve.
+    #[cfg(test)]
+    pub(crate) fn from_xy(x: &BigInt, y: &BigInt) -> Result<Self, PointError> {
+        let zero = BigInt::from(0);
+        let p = C::p();
+        if *x < zero || *x >= p || *y < zero || *y >= p {
+            return Err(PointError::CoordinateOutOfRange)
+        }
+        let x = C::Field::from_bigint(x);
+        let y = C::Field::from_bigint(y);
+        if !is_on_curve::<C>(&x, &y) {
+            return Err(PointError::NotOnCurve)
+        }
+        Ok(Self::from_xy_unchecked(x, y))
+    }
+
+    /// Builds a point without checking it is on the curve. Only for coordinates
+    /// that come out of the curve arithmetic or are known good constants.
+    pub(crate) fn from_xy_unchecked(x: C::Field, y: C::Field) -> Self {
+        Self::Affine {
             x,
             y,
         }
     }
 
-    fn x(&self) -> BigInt {
-        self.x.clone()
+    pub(crate) fn is_infinity(&self) -> bool {
+        matches!(self, Self::Infinity)
+    }
+
+    pub(crate) fn negate(&self) -> Self {
+        match self {
+            Self::Infinity => Self::Infinity,
+            Self::Affine { x, y } => Self::from_xy_unchecked(*x, -*y),
+        }
+    }
+
+    #[cfg(test)]
+    fn double_n_times(self, n: u16) -> Self {
+        JacobianPoint::from_affine(&self)
+            .double_n_times(n)
+            .to_affine()
+    }
+
+    pub(crate) fn x(&self) -> Option<C::Field> {
+        match self {
+            Self::Infinity => None,
+            Self::Affine { x, .. } => Some(*x),
+        }
+    }
+
+    pub(crate) fn y(&self) -> Option<C::Field> {
+        match self {
+            Self::Infinity => None,
+            Self::Affine { y, .. } => Some(*y),
+        }
+    }
+
+    /// Infinity has no y coordinate, so it is never even.
+    pub fn y_is_even(&self) -> bool {
+        self.y().is_some_and(|y| y.is_even())
+    }
+
+    /// The SEC1 encoding: a single 00 byte for infinity, 02 or 03 followed by x
+    /// when compressed, and 04 followed by x and y otherwise.
+    pub fn to_sec1_bytes(&self, compressed: bool) -> Vec<u8> {
+        // https://www.secg.org/sec1-v2.pdf section 2.3.3
+        match self {
+            Self::Infinity => Vec::from([0]),
+            Self::Affine { x, y } if compressed => {
+                let mut bytes = if y.is_even() {
+                    Vec::from([2])
+                } else {
+                    Vec::from([3])
+                };
+                bytes.append(&mut x.to_bytes_be());
+                bytes
+            }
+            Self::Affine { x, y } => {
+                let mut bytes = Vec::from([4]);
+                bytes.append(&mut x.to_bytes_be());
+                bytes.append(&mut y.to_bytes_be());
+                bytes
+            }
+        }
+    }
+}
+
+impl Point<Secp256k1> {
+    /// Parses a SEC1 encoded public key, recovering y from its parity when
+    /// the key is compressed.
+    pub(crate) fn from_sec1_bytes(bytes: &[u8]) -> Result<Self, PointError> {
+        // https://www.secg.org/sec1-v2.pdf section 2.3.4
+        let prefix = *bytes.first().ok_or(PointError::InvalidLength(0))?;
+        match (bytes.len(), prefix) {
+            (33, 2 | 3) => {
+                let x = parse_coordinate(&bytes[1..33])?;
+                let y_squared = x.square() * x + Secp256k1::a() * x + Secp256k1::b();
+                let y = y_squared.sqrt().ok_or(PointError::NotOnCurve)?;
+                let y = if y.is_even() == (prefix == 2) {
+                    y
+                } else {
+                    -y
+                };
+                Ok(Self::from_xy_unchecked(x, y))
+            }
+            (65, 4) => {
+                let x = parse_coordinate(&bytes[1..33])?;
+                let y = parse_coordinate(&bytes[33..65])?;
+                if !is_on_curve::<Secp256k1>(&x, &y) {
+                    return Err(PointError::NotOnCurve)
+                }
+                Ok(Self::from_xy_unchecked(x, y))
+            }
+            (1, 0) => Err(PointError::Infinity),
+            (33 | 65, prefix) => Err(PointError::InvalidPrefix(prefix)),
+            (length, _) => Err(PointError::InvalidLength(length)),
+        }
+    }
+}
+
+fn parse_coordinate(bytes: &[u8]) -> Result<FieldElement, PointError> {
+    FieldElement::from_bytes_be(bytes.try_into().unwrap()).ok_or(PointError::CoordinateOutOfRange)
+}
+
+/// Whether (x, y) satisfies y^2 = x^3 + ax + b.
+fn is_on_curve<C: Curve>(x: &C::Field, y: &C::Field) -> bool {
+    y.square() == x.square() * *x + C::a() * *x + C::b()
+}
+
+/// A point in Jacobian coordinates, where (X, Y, Z) stands for the affine
+/// point (X / Z^2, Y / Z^3). Adding and doubling need no inversion, so the
+/// only one is paid when converting back with `to_affine`.
+#[derive(Debug, Clone)]
+struct JacobianPoint<C: Curve> {
+    x: C::Field,
+    y: C::Field,
+    z: C::Field,
+}
+
+impl<C: Curve> JacobianPoint<C> {
+    fn infinity() -> Self {
+        Self {
+            x: C::Field::one(),
+            y: C::Field::one(),
+            z: C::Field::zero(),
+        }
+    }
+
+    fn from_affine(point: &Point<C>) -> Self {
+        match point {
+            Point::Infinity => Self::infinity(),
+            Point::Affine { x, y } => Self {
+                x: *x,
+                y: *y,
+                z: C::Field::one(),
+            },
+        }
+    }
+
+    fn to_affine(&self) -> Point<C> {
+        if self.is_infinity() {
+            return Point::Infinity
+        }
+        let z_inv = self.z.invert();
+        let z_inv_squared = z_inv.square();
+        Point::from_xy_unchecked(self.x * z_inv_squared, self.y * z_inv_squared * z_inv)
+    }
+
+    fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    fn double(&self) -> Self {
+        // https://hyperelliptic.org/EFD/g1p/auto-shortw-jacobian.html#doubling-dbl-1998-cmo-2
+        if self.is_infinity() || self.y.is_zero() {
+            return Self::infinity()
+        }
+        let y_squared = self.y.square();
+        let s = C::Field::from_u64(4) * self.x * y_squared;
+        let m = C::Field::from_u64(3) * self.x.square() + C::a() * self.z.square().square();
+        let x = m.square() - (s + s);
+        let y = m * (s - x) - C::Field::from_u64(8) * y_squared.square();
+        let z = C::Field::from_u64(2) * self.y * self.z;
+
+        Self {
+            x,
+            y,
+            z,
+        }
     }
 
-    fn y(&self) -> BigInt {
-        self.y.clone()
+    fn add(&self, other: &Self) -> Self {
+        // https://hyperelliptic.org/EFD/g1p/auto-shortw-jacobian.html#addition-add-1998-cmo-2
+        if self.is_infinity() {
+            return other.clone()
+        }
+        if other.is_infinity() {
+            return self.clone()
+        }
+        let z1_squared = self.z.square();
+        let z2_squared = other.z.square();
+        let u1 = self.x * z2_squared;
+        let u2 = other.x * z1_squared;
+        let s1 = self.y * z2_squared * other.z;
+        let s2 = other.y * z1_squared * self.z;
+        let h = u2 - u1;
+        let r = s2 - s1;
+        if h.is_zero() {
+            if r.is_zero() {
+                return self.double()
+            }
+            return Self::infinity()
+        }
+        let h_squared = h.square();
+        let h_cubed = h_squared * h;
+        let u1_h_squared = u1 * h_squared;
+        let x = r.square() - h_cubed - (u1_h_squared + u1_h_squared);
+        let y = r * (u1_h_squared - x) - s1 * h_cubed;
+        let z = h * self.z * other.z;
+
+        Self {
+            x,
+            y,
+            z,
+        }
     }
 
-    fn double_n_times(self, n: u16, p: &BigInt, a: &BigInt) -> Self {
+    fn double_n_times(&self, n: u16) -> Self {
         let mut point = self.clone();
         for _ in 0..n {
-            point = point.clone().add(point, &p, &a)
+            point = point.double()
         }
         point
     }
+}
 
-    pub fn x_to_hex_string(&self) -> String {
-        format!("{:0>64}", self.x.to_str_radix(16).to_uppercase())
+/// A point in homogeneous projective coordinates, where (X, Y, Z) stands for
+/// the affine point (X / Z, Y / Z) and infinity is (0, 1, 0). Addition uses
+/// complete formulas with no special cases, so there is nothing for the
+/// running time to branch on.
+#[derive(Debug, Clone)]
+pub(crate) struct ProjectivePoint<C: Curve> {
+    x: C::Field,
+    y: C::Field,
+    z: C::Field,
+}
+
+impl<C: Curve> ProjectivePoint<C> {
+    pub(crate) fn infinity() -> Self {
+        Self {
+            x: C::Field::zero(),
+            y: C::Field::one(),
+            z: C::Field::zero(),
+        }
     }
 
-    pub fn y_to_hex_string(&self) -> String {
-        format!("{:0>64}", self.y.to_str_radix(16).to_uppercase())
+    pub(crate) fn from_affine(point: &Point<C>) -> Self {
+        match point {
+            Point::Infinity => Self::infinity(),
+            Point::Affine { x, y } => Self {
+                x: *x,
+                y: *y,
+                z: C::Field::one(),
+            },
+        }
     }
 
-    pub fn x_bytes(&self) -> Vec<u8> {
-        self.x.to_biguint().unwrap().to_bytes_be()
+    pub(crate) fn to_affine(&self) -> Point<C> {
+        if self.z.is_zero() {
+            return Point::Infinity
+        }
+        let z_inv = self.z.invert();
+        Point::from_xy_unchecked(self.x * z_inv, self.y * z_inv)
     }
 
-    pub fn y_bytes(&self) -> Vec<u8> {
-        self.y.to_biguint().unwrap().to_bytes_be()
+    /// Picks `b` when `choice` is set and `a` otherwise, without branching on `choice`.
+    pub(crate) fn conditional_select(a: &Self, b: &Self, choice: bool) -> Self {
+        Self {
+            x: C::Field::conditional_select(&a.x, &b.x, choice),
+            y: C::Field::conditional_select(&a.y, &b.y, choice),
+            z: C::Field::conditional_select(&a.z, &b.z, choice),
+        }
     }
 
-    pub fn y_is_even(&self) -> bool {
-        self.y.clone() % BigInt::from(2) == BigInt::from(0)
+    /// Works for any two points, including doubling and infinity, as long as
+    /// the curve has no points of order two.
+    pub(crate) fn add(&self, other: &Self) -> Self {
+        // https://eprint.iacr.org/2015/1060.pdf algorithm 1
+        let a = C::a();
+        let b3 = C::Field::from_u64(3) * C::b();
+        let (x1, y1, z1) = (self.x, self.y, self.z);
+        let (x2, y2, z2) = (other.x, other.y, other.z);
+
+        let mut t0 = x1 * x2;
+        let mut t1 = y1 * y2;
+        let mut t2 = z1 * z2;
+        let mut t3 = x1 + y1;
+        let mut t4 = x2 + y2;
+        t3 = t3 * t4;
+        t4 = t0 + t1;
+        t3 = t3 - t4;
+        t4 = x1 + z1;
+        let mut t5 = x2 + z2;
+        t4 = t4 * t5;
+        t5 = t0 + t2;
+        t4 = t4 - t5;
+        t5 = y1 + z1;
+        let mut x3 = y2 + z2;
+        t5 = t5 * x3;
+        x3 = t1 + t2;
+        t5 = t5 - x3;
+        let mut z3 = a * t4;
+        x3 = b3 * t2;
+        z3 = x3 + z3;
+        x3 = t1 - z3;
+        z3 = t1 + z3;
+        let mut y3 = x3 * z3;
+        t1 = t0 + t0;
+        t1 = t1 + t0;
+        t2 = a * t2;
+        t4 = b3 * t4;
+        t1 = t1 + t2;
+        t2 = t0 - t2;
+        t2 = a * t2;
+        t4 = t4 + t2;
+        t0 = t1 * t4;
+        y3 = y3 + t0;
+        t0 = t5 * t4;
+        x3 = t3 * x3;
+        x3 = x3 - t0;
+        t0 = t3 * t1;
+        z3 = t5 * z3;
+        z3 = z3 + t0;
+
+        Self {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
     }
 }
 
-impl Clone for Point {
-    fn clone(&self) -> Self {
-        Self { 
-            x: self.x.clone(), 
-            y: self.y.clone(), 
+/// Works out k1 P1 + k2 P2 + ... with Strauss's method: every point shares
+/// one run of doublings and adds a small multiple of itself, looked up in a
+/// table, once per four bits. Much cheaper than multiplying each point and
+/// adding the results, but only for public scalars.
+pub(crate) fn multi_scalar_multiply<C: Curve>(terms: &[(Point<C>, BigInt)]) -> Point<C> {
+    // https://doi.org/10.2307/2310929
+    const WINDOW_BITS: u64 = 4;
+    let order = C::n() * C::h();
+    let scalars: Vec<BigInt> = terms.iter().map(|(_, k)| positive_mod(k, &order)).collect();
+    let tables: Vec<Vec<JacobianPoint<C>>> = terms
+        .iter()
+        .map(|(point, _)| {
+            let point = JacobianPoint::from_affine(point);
+            let mut table = vec![JacobianPoint::infinity()];
+            for i in 1..1 << WINDOW_BITS {
+                table.push(table[i - 1].add(&point));
+            }
+            table
+        })
+        .collect();
+
+    let bits = scalars.iter().map(|k| k.bits()).max().unwrap_or(0);
+    let mut total = JacobianPoint::infinity();
+    for window in (0..bits.div_ceil(WINDOW_BITS)).rev() {
+        total = total.double_n_times(WINDOW_BITS as u16);
+        for (scalar, table) in scalars.iter().zip(&tables) {
+            let digit = (0..WINDOW_BITS)
+                .filter(|bit| scalar.bit(window * WINDOW_BITS + bit))
+                .fold(0, |digit, bit| digit | 1 << bit);
+            if digit != 0 {
+                total = total.add(&table[digit]);
+            }
         }
     }
+    total.to_affine()
 }
 
 pub(crate) fn positive_mod(num: &BigInt, p: &BigInt) -> BigInt {
@@ -121,7 +458,7 @@ pub(crate) fn positive_mod(num: &BigInt, p: &BigInt) -> BigInt {
 fn extended_euclidean_algeorithm(num: &BigInt, p: &BigInt, i_num: &BigInt, j_num: &BigInt, i_p: &BigInt, j_p: &BigInt, target: &BigInt) -> (BigInt, BigInt) {
     // https://youtu.be/IwRtISxAHY4
     
-    let num = positive_mod(&num, &p);
+    let num = positive_mod(num, p);
     let multiples = p / num.clone();
     let remainder = p % num.clone();
     let i_remainder = i_p - multiples.clone() * i_num;
@@ -129,7 +466,7 @@ fn extended_euclidean_algeorithm(num: &BigInt, p: &BigInt, i_num: &BigInt, j_num
     if remainder == *target {        
         (i_remainder, j_remainder)
     } else {
-        extended_euclidean_algeorithm(&remainder, &num, &i_remainder, &j_remainder, &i_num, &j_num, &target)
+        extended_euclidean_algeorithm(&remainder, &num, &i_remainder, &j_remainder, i_num, j_num, target)
     }
 }
 
@@ -159,38 +496,33 @@ fn powers_of_two(n: &BigInt) -> Vec<u16> {
 }
 
 // Calculates the modulo inverse of a number with a given p
-fn modulo_inverse(num: &BigInt, p: &BigInt) -> BigInt {
+pub(crate) fn modulo_inverse(num: &BigInt, p: &BigInt) -> BigInt {
     if *num == BigInt::from(1) {
         return num.clone()
     }
-    let (_, j) = extended_euclidean_algeorithm(&num, &p, &BigInt::from(0), &BigInt::from(1), &BigInt::from(1), &BigInt::from(0), &BigInt::from(1));
-    positive_mod(&j, &p)
+    let (_, j) = extended_euclidean_algeorithm(num, p, &BigInt::from(0), &BigInt::from(1), &BigInt::from(1), &BigInt::from(0), &BigInt::from(1));
+    positive_mod(&j, p)
 }
 
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use crate::curve::{Secp256k1, SmallCurve};
 
-    fn test_point(x: u32, y: u32) -> Point {
-        let x = BigInt::from(x);
-        let y = BigInt::from(y);
-        Point {
-            x,
-            y,
-        }
+    // y^2 = x^3 + 7 over F_17, where (1, 5) has order 9
+    type Toy17 = SmallCurve<17, 0, 7, 1, 5, 9, 2>;
+
+    fn test_point(x: u64, y: u64) -> Point<Toy17> {
+        Point::from_xy(&BigInt::from(x), &BigInt::from(y)).unwrap()
     }
 
     #[test]
     fn test_point_addition_to_itself() {
         let point1 = test_point(1, 5);
         let point2 = test_point(1, 5);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
 
-        let actual = point1.add(point2, &p, &a);
+        let actual = point1.add(point2);
 
         let expected = test_point(2, 10);
         assert_eq!(actual, expected);
@@ -200,12 +532,8 @@ mod tests {
     fn test_point_addition_other_modulo() {
         let point1 = test_point(1, 5);
         let point2 = test_point(2, 10);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
 
-        let actual = point1.add(point2, &p, &a);
+        let actual = point1.add(point2);
 
         let expected = test_point(5, 9);
         assert_eq!(actual, expected);
@@ -215,17 +543,292 @@ mod tests {
     fn test_point_addition_other() {
         let point1 = test_point(1, 5);
         let point2 = test_point(2, 7);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
 
-        let actual = point1.add(point2, &p, &a);
+        let actual = point1.add(point2);
 
         let expected = test_point(1, 12);
         assert_eq!(actual, expected);
     }
 
+    /// The plain affine chord-and-tangent rule, used as a reference for the
+    /// Jacobian arithmetic.
+    fn affine_add<C: Curve>(p1: &Point<C>, p2: &Point<C>) -> Point<C> {
+        let (x1, y1) = (p1.x().unwrap(), p1.y().unwrap());
+        let (x2, y2) = (p2.x().unwrap(), p2.y().unwrap());
+        let s = if x1 == x2 {
+            (C::Field::from_u64(3) * x1.square() + C::a()) * (C::Field::from_u64(2) * y1).invert()
+        } else {
+            (y2 - y1) * (x2 - x1).invert()
+        };
+        let x = s.square() - (x1 + x2);
+        let y = -(y1 + s * (x - x1));
+        Point::from_xy_unchecked(x, y)
+    }
+
+    #[test]
+    fn test_multiplication_matches_affine_toy_curve() {
+        let point = test_point(1, 5);
+
+        let mut expected = point.clone();
+        // (1, 5) has order 9, so stop before reaching infinity
+        for factor in 2..9 {
+            expected = affine_add(&expected, &point);
+            let actual = point.clone().multiply(&BigInt::from(factor));
+            assert_eq!(actual, expected, "factor {}", factor);
+        }
+    }
+
+    #[test]
+    fn test_multiplication_matches_affine_secp256k1() {
+        let point = Secp256k1::generator();
+
+        let mut expected = point.clone();
+        for factor in 2..40 {
+            expected = affine_add(&expected, &point);
+            let actual = point.clone().multiply(&BigInt::from(factor));
+            assert_eq!(actual, expected, "factor {}", factor);
+        }
+    }
+
+    #[test]
+    fn test_point_plus_its_negation_is_infinity() {
+        let point = test_point(1, 5);
+
+        let actual = point.clone().add(point.negate());
+
+        assert_eq!(actual, Point::Infinity);
+    }
+
+    #[test]
+    fn test_secp256k1_point_plus_its_negation_is_infinity() {
+        let point = Secp256k1::generator().multiply(&BigInt::from(12345));
+
+        let actual = point.clone().add(point.negate());
+
+        assert_eq!(actual, Point::Infinity);
+    }
+
+    #[test]
+    fn test_infinity_is_the_identity() {
+        let point = test_point(1, 5);
+
+        assert_eq!(point.clone().add(Point::Infinity), point);
+        assert_eq!(Point::Infinity.add(point.clone()), point);
+        assert_eq!(Point::<Toy17>::Infinity.add(Point::Infinity), Point::Infinity);
+        assert_eq!(Point::<Toy17>::Infinity.multiply(&BigInt::from(5)), Point::Infinity);
+    }
+
+    #[test]
+    fn test_multiplication_by_zero() {
+        let actual = Secp256k1::generator().multiply(&BigInt::from(0));
+
+        assert_eq!(actual, Point::Infinity);
+    }
+
+    #[test]
+    fn test_multiplication_by_order() {
+        let actual = Secp256k1::generator().multiply(&Secp256k1::n());
+
+        assert_eq!(actual, Point::Infinity);
+    }
+
+    #[test]
+    fn test_multiplication_by_order_plus_one() {
+        let actual = Secp256k1::generator().multiply(&(Secp256k1::n() + 1));
+
+        assert_eq!(actual, Secp256k1::generator());
+    }
+
+    #[test]
+    fn test_multiplication_by_order_toy_curve() {
+        let actual = Toy17::generator().multiply(&Toy17::n());
+
+        assert_eq!(actual, Point::Infinity);
+    }
+
+    #[test]
+    fn test_infinity_serialization() {
+        let point = Point::<Secp256k1>::Infinity;
+
+        assert_eq!(point.x(), None);
+        assert_eq!(point.y(), None);
+        assert!(!point.y_is_even());
+        assert_eq!(point.to_sec1_bytes(true), Vec::from([0]));
+        assert_eq!(point.to_sec1_bytes(false), Vec::from([0]));
+    }
+
+    #[test]
+    fn test_multiply_negative_scalars() {
+        let g = Toy17::generator();
+        for k in [1, 5, 18, 40] {
+            assert_eq!(g.clone().multiply(&BigInt::from(-k)), g.clone().multiply(&BigInt::from(k)).negate(), "k {}", k);
+        }
+
+        let g = Secp256k1::generator();
+        let k = BigInt::from(12345);
+        assert_eq!(g.clone().multiply(&-&k), g.clone().multiply(&k).negate());
+        assert_eq!(g.clone().multiply(&(Secp256k1::n() + 1)), g);
+    }
+
+    #[test]
+    fn test_multi_scalar_multiply_toy_curve() {
+        let g = Toy17::generator();
+        let points = [g.clone(), g.clone().multiply(&BigInt::from(4)), g.negate()];
+
+        for (a, b, c) in [(0, 0, 0), (1, 2, 3), (8, 8, 8), (5, -7, 30), (9, 18, 27)] {
+            let scalars = [BigInt::from(a), BigInt::from(b), BigInt::from(c)];
+            let expected = points.iter().zip(&scalars).fold(Point::Infinity, |total, (point, scalar)| {
+                total.add(point.clone().multiply(&positive_mod(scalar, &BigInt::from(18))))
+            });
+            let terms: Vec<_> = points.iter().cloned().zip(scalars).collect();
+
+            assert_eq!(multi_scalar_multiply(&terms), expected, "scalars {}, {}, {}", a, b, c);
+        }
+    }
+
+    #[test]
+    fn test_multi_scalar_multiply_secp256k1() {
+        let n = Secp256k1::n();
+        let g = Secp256k1::generator();
+        let terms = [
+            (g.clone(), BigInt::parse_bytes(b"038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14776", 16).unwrap()),
+            (g.clone().multiply(&BigInt::from(12345)), &n - 1),
+            (g.clone().multiply(&BigInt::from(2).pow(200)), BigInt::from(0xdeadbeefu64)),
+            (Point::Infinity, BigInt::from(5)),
+        ];
+
+        let expected = terms.iter().fold(Point::Infinity, |total, (point, scalar)| {
+            total.add(point.clone().multiply(scalar))
+        });
+
+        assert_eq!(multi_scalar_multiply(&terms), expected);
+        assert_eq!(multi_scalar_multiply::<Secp256k1>(&[]), Point::Infinity);
+        // G - G
+        assert_eq!(multi_scalar_multiply(&[(g.clone(), BigInt::from(1)), (g, &n - 1)]), Point::Infinity);
+    }
+
+    fn hex_to_bytes(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_sec1_round_trip() {
+        for k in [1, 2, 3, 12345, 987654321] {
+            let point = Secp256k1::generator().multiply(&BigInt::from(k));
+
+            let compressed = Point::from_sec1_bytes(&point.to_sec1_bytes(true));
+            let uncompressed = Point::from_sec1_bytes(&point.to_sec1_bytes(false));
+
+            assert_eq!(compressed, Ok(point.clone()), "k = {}", k);
+            assert_eq!(uncompressed, Ok(point), "k = {}", k);
+        }
+    }
+
+    #[test]
+    fn test_sec1_decompress_even() {
+        let bytes = hex_to_bytes("0202a406624211f2abbdc68da3df929f938c3399dd79fac1b51b0e4ad1d26a47aa");
+
+        let point = Point::from_sec1_bytes(&bytes).unwrap();
+
+        let expected_y = hex_to_bytes("9F3BC9F3948A19DABB796A2A744AAE50367CE38A3E6B60AE7D72159CAEB0C102");
+        assert_eq!(point.y().unwrap().to_bytes().to_vec(), expected_y);
+    }
+
+    #[test]
+    fn test_sec1_decompress_odd() {
+        let bytes = hex_to_bytes("032e294c59fd0b721437a76b8f133e6bb79a222e6488a5296eae96599750f75120");
+
+        let point = Point::from_sec1_bytes(&bytes).unwrap();
+
+        assert!(!point.y_is_even());
+        assert_eq!(point.to_sec1_bytes(true), bytes);
+    }
+
+    #[test]
+    fn test_sec1_rejects_x_with_no_point() {
+        let mut bytes = vec![0u8; 33];
+        bytes[0] = 2;
+        bytes[32] = 5;
+
+        assert_eq!(Point::from_sec1_bytes(&bytes), Err(PointError::NotOnCurve));
+    }
+
+    #[test]
+    fn test_sec1_rejects_uncompressed_off_curve() {
+        let mut bytes = Secp256k1::generator().to_sec1_bytes(false);
+        bytes[64] ^= 1;
+
+        assert_eq!(Point::from_sec1_bytes(&bytes), Err(PointError::NotOnCurve));
+    }
+
+    #[test]
+    fn test_sec1_rejects_x_out_of_range() {
+        let mut bytes = vec![0xFFu8; 33];
+        bytes[0] = 2;
+
+        assert_eq!(Point::from_sec1_bytes(&bytes), Err(PointError::CoordinateOutOfRange));
+    }
+
+    #[test]
+    fn test_sec1_rejects_bad_prefix_and_length() {
+        let compressed = Secp256k1::generator().to_sec1_bytes(true);
+        let mut hybrid = Secp256k1::generator().to_sec1_bytes(false);
+        hybrid[0] = 6;
+
+        assert_eq!(Point::from_sec1_bytes(&hybrid), Err(PointError::InvalidPrefix(6)));
+        assert_eq!(Point::from_sec1_bytes(&compressed[..32]), Err(PointError::InvalidLength(32)));
+        assert_eq!(Point::from_sec1_bytes(&[]), Err(PointError::InvalidLength(0)));
+        assert_eq!(Point::from_sec1_bytes(&[1]), Err(PointError::InvalidLength(1)));
+    }
+
+    #[test]
+    fn test_from_xy_accepts_point_on_curve() {
+        let x = BigInt::parse_bytes(b"79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798", 16).unwrap();
+        let y = BigInt::parse_bytes(b"483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8", 16).unwrap();
+
+        assert_eq!(Point::<Secp256k1>::from_xy(&x, &y), Ok(Secp256k1::generator()));
+    }
+
+    #[test]
+    fn test_from_xy_rejects_point_off_curve() {
+        let actual = Point::<Toy17>::from_xy(&BigInt::from(1), &BigInt::from(6));
+
+        assert_eq!(actual, Err(PointError::NotOnCurve));
+    }
+
+    #[test]
+    fn test_from_xy_rejects_out_of_range() {
+        let p = Toy17::p();
+
+        // (1 + 17, 5) and (1, 5 - 17) are (1, 5) once reduced, but still not valid input
+        let too_big = Point::<Toy17>::from_xy(&(BigInt::from(1) + &p), &BigInt::from(5));
+        let negative = Point::<Toy17>::from_xy(&BigInt::from(1), &(BigInt::from(5) - &p));
+        let sentinel = Point::<Toy17>::from_xy(&BigInt::from(-1), &BigInt::from(-1));
+
+        assert_eq!(too_big, Err(PointError::CoordinateOutOfRange));
+        assert_eq!(negative, Err(PointError::CoordinateOutOfRange));
+        assert_eq!(sentinel, Err(PointError::CoordinateOutOfRange));
+    }
+
+    #[test]
+    fn test_from_xy_rejects_invalid_curve_point() {
+        // (0, 1) lies on y^2 = x^3 + 1, a weaker curve sharing secp256k1's
+        // addition formulas, which an invalid curve attack would feed in
+        let actual = Point::<Secp256k1>::from_xy(&BigInt::from(0), &BigInt::from(1));
+
+        assert_eq!(actual, Err(PointError::NotOnCurve));
+    }
+
+    #[test]
+    fn test_from_sec1_bytes_rejects_infinity() {
+        let bytes = Point::<Secp256k1>::Infinity.to_sec1_bytes(true);
+
+        assert_eq!(Point::from_sec1_bytes(&bytes), Err(PointError::Infinity));
+    }
+
     #[test]
     fn test_powers_of_two() {
         let actual = powers_of_two(&BigInt::from(37));
@@ -236,12 +839,8 @@ mod tests {
     #[test]
     fn test_double_n_times_0() {
         let point = test_point(1, 5);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
         
-        let actual = point.clone().double_n_times(0, &p, &a);
+        let actual = point.clone().double_n_times(0);
 
         let expected = point;
 
@@ -251,12 +850,8 @@ mod tests {
     #[test]
     fn test_double_n_times_1() {
         let point = test_point(1, 5);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
         
-        let actual = point.clone().double_n_times(1, &p, &a);
+        let actual = point.clone().double_n_times(1);
 
         let expected = test_point(2, 10);
 
@@ -266,12 +861,8 @@ mod tests {
     #[test]
     fn test_double_n_times_2() {
         let point = test_point(1, 5);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
         
-        let actual = point.clone().double_n_times(2, &p, &a);
+        let actual = point.clone().double_n_times(2);
 
         let expected = test_point(12, 1);
 
@@ -282,12 +873,8 @@ mod tests {
     fn test_multiplication_int_1() {
         let point = test_point(1, 5);
         let factor = BigInt::from(1);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
 
-        let actual = point.multiply(&factor, &p, &a);
+        let actual = point.multiply(&factor);
 
         let expected = test_point(1, 5);
 
@@ -298,12 +885,8 @@ mod tests {
     fn test_multiplication_int_1_rev() {
         let point = test_point(1, 5);
         let factor = BigInt::from(1);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
 
-        let actual = point.multiply(&factor, &p, &a);
+        let actual = point.multiply(&factor);
 
         let expected = test_point(1, 5);
 
@@ -314,12 +897,8 @@ mod tests {
     fn test_multiplication_int_2() {
         let point = test_point(1, 5);
         let factor = BigInt::from(2);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
 
-        let actual = point.multiply(&factor, &p, &a);
+        let actual = point.multiply(&factor);
 
         let expected = test_point(2, 10);
 
@@ -330,12 +909,8 @@ mod tests {
     fn test_multiplication_int_2_rev() {
         let point = test_point(1, 5);
         let factor = BigInt::from(2);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
 
-        let actual = point.multiply(&factor, &p, &a);
+        let actual = point.multiply(&factor);
 
         let expected = test_point(2, 10);
 
@@ -346,12 +921,8 @@ mod tests {
     fn test_multiplication_int_21() {
         let point = test_point(1, 5);
         let factor = BigInt::from(21);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
 
-        let actual = point.multiply(&factor, &p, &a);
+        let actual = point.multiply(&factor);
 
         let expected = test_point(5, 9);
 
@@ -362,12 +933,8 @@ mod tests {
     fn test_multiplication_int_21_rev() {
         let point = test_point(1, 5);
         let factor = BigInt::from(21);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
 
-        let actual = point.multiply(&factor, &p, &a);
+        let actual = point.multiply(&factor);
 
         let expected = test_point(5, 9);
 
@@ -378,12 +945,8 @@ mod tests {
     fn test_multiplication_int_40() {
         let point = test_point(1, 5);
         let factor = BigInt::from(40);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
 
-        let actual = point.multiply(&factor, &p, &a);
+        let actual = point.multiply(&factor);
 
         let expected = test_point(12, 1);
 
@@ -394,12 +957,8 @@ mod tests {
     fn test_multiplication_int_40_rev() {
         let point = test_point(1, 5);
         let factor = BigInt::from(40);
-        let a = BigInt::from(0);
-        let b = BigInt::from(7);
-        let p = BigInt::from(17);
-        let n = BigInt::from(18);
 
-        let actual = point.multiply(&factor, &p, &a);
+        let actual = point.multiply(&factor);
 
         let expected = test_point(12, 1);
 
@@ -438,23 +997,6 @@ mod tests {
 
 
 
-    // #[test]
-    // fn test_start_public_key_calculations() {
-    //     let private_key = BigInt::parse_bytes(b"1E99423A4ED27608A15A2616A2B0E9E52CED330AC530EDCC32C8FFC6A526AEDD", 16).unwrap();
-
-    //     let point = Point::generator_point();
-
-    //     // K = kG
-    //     // public_key = private_key * generater_point
-    //     let public_key = point * private_key;
-
-    //     let expected_x = "F028892BAD7ED57D2FB57BF33081D5CFCF6F9ED3D3D7F159C2E2FFF579DC341A".to_string();
-    //     let expected_y = "07CF33DA18BD734C600B96A72BBC4749D5141C90EC8AC328AE52DDFE2E505BDB".to_string();
-
-    //     assert_eq!(public_key.x_to_hex_string(), expected_x);
-    //     assert_eq!(public_key.y_to_hex_string(), expected_y);
-    // }
-
     // #[test]
     // fn test_lkj() {
     //     let point = Point::from_xy(BigInt::from(4), BigInt::from(5));
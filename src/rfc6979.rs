@@ -0,0 +1,195 @@
+use hmac::{Hmac, Mac};
+use num_bigint::{BigInt, Sign};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Deterministic ECDSA nonces as described in RFC 6979, using HMAC-SHA256.
+/// The nonce depends only on the private key and the message hash, so a bad
+/// random number generator can't leak the key.
+/// Each call to `next` gives the next candidate k in [1, q), for when a
+/// candidate gives a signature with r or s of zero.
+pub(crate) struct NonceGenerator {
+    k: [u8; 32],
+    v: [u8; 32],
+    q: BigInt,
+    started: bool,
+}
+
+impl NonceGenerator {
+    /// `q` is the order of the group, `x` the private key and `h1` the hash of the message.
+    /// `extra_entropy` is mixed in after the key and hash the way libsecp256k1
+    /// does, which keeps the nonce safe if the same key and message are
+    /// signed on a machine with broken hashing.
+    pub(crate) fn new(q: &BigInt, x: &BigInt, h1: &[u8], extra_entropy: Option<&[u8; 32]>) -> Self {
+        // https://www.rfc-editor.org/rfc/rfc6979#section-3.2
+        let mut seed = int2octets(x, q);
+        seed.append(&mut bits2octets(h1, q));
+        if let Some(extra_entropy) = extra_entropy {
+            seed.extend_from_slice(extra_entropy);
+        }
+
+        let mut generator = Self {
+            k: [0x00; 32],
+            v: [0x01; 32],
+            q: q.clone(),
+            started: false,
+        };
+        generator.k = generator.hmac(&[&generator.v, &[0x00], &seed]);
+        generator.v = generator.hmac(&[&generator.v]);
+        generator.k = generator.hmac(&[&generator.v, &[0x01], &seed]);
+        generator.v = generator.hmac(&[&generator.v]);
+        generator
+    }
+
+    fn hmac(&self, parts: &[&[u8]]) -> [u8; 32] {
+        let mut mac = HmacSha256::new_from_slice(&self.k).unwrap();
+        for part in parts {
+            mac.update(part);
+        }
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Moves on from a rejected candidate, step h.3 of the RFC.
+    fn reseed(&mut self) {
+        self.k = self.hmac(&[&self.v, &[0x00]]);
+        self.v = self.hmac(&[&self.v]);
+    }
+}
+
+impl Iterator for NonceGenerator {
+    type Item = BigInt;
+
+    fn next(&mut self) -> Option<BigInt> {
+        if self.started {
+            self.reseed();
+        }
+        self.started = true;
+
+        let qlen = self.q.bits() as usize;
+        loop {
+            let mut t = Vec::new();
+            while t.len() * 8 < qlen {
+                self.v = self.hmac(&[&self.v]);
+                t.extend_from_slice(&self.v);
+            }
+            let k = bits2int(&t, qlen);
+            if k >= BigInt::from(1) && k < self.q {
+                return Some(k)
+            }
+            self.reseed();
+        }
+    }
+}
+
+/// Reads the leftmost qlen bits of the input as a big endian integer.
+fn bits2int(bytes: &[u8], qlen: usize) -> BigInt {
+    let value = BigInt::from_bytes_be(Sign::Plus, bytes);
+    let blen = bytes.len() * 8;
+    if blen > qlen {
+        value >> (blen - qlen)
+    } else {
+        value
+    }
+}
+
+/// Writes an integer as big endian bytes, padded to the byte length of q.
+fn int2octets(value: &BigInt, q: &BigInt) -> Vec<u8> {
+    let rlen = (q.bits() as usize).div_ceil(8);
+    let (_, bytes) = value.to_bytes_be();
+    let mut padded = vec![0u8; rlen.saturating_sub(bytes.len())];
+    padded.extend_from_slice(&bytes[bytes.len().saturating_sub(rlen)..]);
+    padded
+}
+
+fn bits2octets(bytes: &[u8], q: &BigInt) -> Vec<u8> {
+    let z1 = bits2int(bytes, q.bits() as usize);
+    int2octets(&(z1 % q), q)
+}
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use sha2::Digest;
+
+    use crate::curve::{Curve, Secp256k1};
+
+    fn hex_to_bigint(text: &str) -> BigInt {
+        BigInt::parse_bytes(text.as_bytes(), 16).unwrap()
+    }
+
+    fn first_nonce(q: &BigInt, x: &BigInt, message: &[u8]) -> BigInt {
+        let h1 = Sha256::digest(message);
+        NonceGenerator::new(q, x, &h1, None).next().unwrap()
+    }
+
+    #[test]
+    fn test_rfc_detailed_example() {
+        // https://www.rfc-editor.org/rfc/rfc6979#appendix-A.1
+        let q = hex_to_bigint("4000000000000000000020108A2E0CC0D99F8A5EF");
+        let x = hex_to_bigint("09A4D6792295A7F730FC3F2B49CBC0F62E862272F");
+
+        let actual = first_nonce(&q, &x, b"sample");
+
+        let expected = hex_to_bigint("23AF4074C90A02B3FE61D286D5C87F425E6BDD81B");
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_rfc_p256_sha256() {
+        // https://www.rfc-editor.org/rfc/rfc6979#appendix-A.2.5
+        let q = hex_to_bigint("FFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551");
+        let x = hex_to_bigint("C9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721");
+
+        let sample = first_nonce(&q, &x, b"sample");
+        let test = first_nonce(&q, &x, b"test");
+
+        assert_eq!(sample, hex_to_bigint("A6E3C57DD01ABE90086538398355DD4C3B17AA873382B0F24D6129493D8AAD60"));
+        assert_eq!(test, hex_to_bigint("D16B6AE827F17175E040871A1C7EC3500192C4C92677336EC2537ACAEE0008E0"));
+    }
+
+    #[test]
+    fn test_secp256k1_vectors() {
+        // the secp256k1 vectors used by python-ecdsa, Trezor and bitcoinjs
+        let n = Secp256k1::n();
+        let vectors = [
+            (BigInt::from(1), "Satoshi Nakamoto", "8F8A276C19F4149656B280621E358CCE24F5F52542772691EE69063B74F15D15"),
+            (BigInt::from(1), "All those moments will be lost in time, like tears in rain. Time to die...", "38AA22D72376B4DBC472E06C3BA403EE0A394DA63FC58D88686C611ABA98D6B3"),
+            (&n - 1, "Satoshi Nakamoto", "33A19B60E25FB6F4435AF53A3D42D493644827367E6453928554F43E49AA6F90"),
+            (hex_to_bigint("f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181"), "Alan Turing", "525A82B70E67874398067543FD84C83D30C175FDC45FDEEE082FE13B1D7CFDF1"),
+        ];
+
+        for (x, message, expected) in vectors {
+            let actual = first_nonce(&n, &x, message.as_bytes());
+            assert_eq!(actual, hex_to_bigint(expected), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_extra_entropy_changes_nonce() {
+        let n = Secp256k1::n();
+        let x = BigInt::from(1);
+        let h1 = Sha256::digest(b"Satoshi Nakamoto");
+
+        let plain = NonceGenerator::new(&n, &x, &h1, None).next().unwrap();
+        let zero_entropy = NonceGenerator::new(&n, &x, &h1, Some(&[0; 32])).next().unwrap();
+        let other_entropy = NonceGenerator::new(&n, &x, &h1, Some(&[1; 32])).next().unwrap();
+
+        assert_ne!(plain, zero_entropy);
+        assert_ne!(zero_entropy, other_entropy);
+        assert_eq!(zero_entropy, NonceGenerator::new(&n, &x, &h1, Some(&[0; 32])).next().unwrap());
+    }
+
+    #[test]
+    fn test_next_candidates_differ() {
+        let n = Secp256k1::n();
+        let h1 = Sha256::digest(b"Satoshi Nakamoto");
+        let mut generator = NonceGenerator::new(&n, &BigInt::from(1), &h1, None);
+
+        let first = generator.next().unwrap();
+        let second = generator.next().unwrap();
+
+        assert_ne!(first, second);
+    }
+}
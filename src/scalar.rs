@@ -0,0 +1,238 @@
+use std::ops::{Add, Mul, Neg};
+
+use num_bigint::{BigInt, Sign};
+
+use crate::curve::{Curve, Secp256k1};
+use crate::field::{add_limbs, select, sub_limbs};
+
+// n = FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
+const N: [u64; 4] = [
+    0xBFD25E8CD0364141,
+    0xBAAEDCE6AF48A03B,
+    0xFFFFFFFFFFFFFFFE,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+/// n - 2, the exponent used for inversion by Fermat's little theorem
+const N_MINUS_2: [u64; 4] = [
+    0xBFD25E8CD036413F,
+    0xBAAEDCE6AF48A03B,
+    0xFFFFFFFFFFFFFFFE,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+/// 2^512 mod n, which takes a Montgomery product back to a plain one
+const R2: [u64; 4] = [
+    0x896CF21467D7D140,
+    0x741496C20E7CF878,
+    0xE697F5E45BCD07C6,
+    0x9D671CD581C69BC5,
+];
+
+/// -n^-1 mod 2^64
+const N_INV: u64 = 0x4B0DFF665588B13F;
+
+/// An integer mod n, the order of the secp256k1 group, stored as four little
+/// endian 64 bit limbs. Private keys and nonces are worked on as these, so
+/// that the arithmetic on them costs the same whatever their value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Scalar([u64; 4]);
+
+impl Scalar {
+    pub(crate) fn zero() -> Self {
+        Self([0; 4])
+    }
+
+    pub(crate) fn one() -> Self {
+        Self([1, 0, 0, 0])
+    }
+
+    /// Reads 32 big endian bytes, reducing them mod n. n is more than 2^255,
+    /// so taking n away once is always enough.
+    pub(crate) fn from_bytes_be(bytes: &[u8; 32]) -> Self {
+        let mut limbs = [0u64; 4];
+        for (i, chunk) in bytes.rchunks(8).enumerate() {
+            limbs[i] = u64::from_be_bytes(chunk.try_into().unwrap());
+        }
+        let (reduced, borrow) = sub_limbs(&limbs, &N);
+        Self(select(borrow, &limbs, &reduced))
+    }
+
+    /// Values from 0 to 2^256 - 1 are reduced by `from_bytes_be`. Anything
+    /// else goes through BigInt division, which is only ever the case for
+    /// values that aren't secret.
+    pub(crate) fn from_bigint(value: &BigInt) -> Self {
+        let (sign, bytes) = value.to_bytes_be();
+        if sign == Sign::Minus || bytes.len() > 32 {
+            let n = Secp256k1::n();
+            return Self::from_bigint(&(((value % &n) + &n) % &n))
+        }
+        let mut padded = [0u8; 32];
+        padded[32 - bytes.len()..].copy_from_slice(&bytes);
+        Self::from_bytes_be(&padded)
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, chunk) in bytes.rchunks_mut(8).enumerate() {
+            chunk.copy_from_slice(&self.0[i].to_be_bytes());
+        }
+        bytes
+    }
+
+    pub(crate) fn to_bigint(self) -> BigInt {
+        BigInt::from_bytes_be(Sign::Plus, &self.to_bytes())
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        self.0.iter().fold(0, |acc, limb| acc | limb) == 0
+    }
+
+    /// Picks `b` when `choice` is set and `a` otherwise, without branching on `choice`.
+    pub(crate) fn conditional_select(a: &Self, b: &Self, choice: bool) -> Self {
+        Self(select(choice, &b.0, &a.0))
+    }
+
+    /// The inverse by Fermat's little theorem, self^(n - 2). The exponent is
+    /// fixed, so the squarings and multiplications don't depend on self the
+    /// way the steps of the extended Euclidean algorithm do.
+    /// Zero has no inverse, and comes back as zero.
+    pub(crate) fn invert(&self) -> Self {
+        let mut result = Self::one();
+        for limb in N_MINUS_2.iter().rev() {
+            for bit in (0..64).rev() {
+                result = result * result;
+                if (limb >> bit) & 1 == 1 {
+                    result = result * *self;
+                }
+            }
+        }
+        result
+    }
+
+    /// a * b * 2^-256 mod n, by word by word Montgomery reduction.
+    fn montgomery_multiply(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+        let mut t = [0u64; 6];
+        for b_limb in b {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let v = t[j] as u128 + a[j] as u128 * *b_limb as u128 + carry;
+                t[j] = v as u64;
+                carry = v >> 64;
+            }
+            let v = t[4] as u128 + carry;
+            t[4] = v as u64;
+            t[5] = (v >> 64) as u64;
+
+            // adding m * n makes the lowest limb zero, so it can be shifted out
+            let m = t[0].wrapping_mul(N_INV);
+            let mut carry = (t[0] as u128 + m as u128 * N[0] as u128) >> 64;
+            for j in 1..4 {
+                let v = t[j] as u128 + m as u128 * N[j] as u128 + carry;
+                t[j - 1] = v as u64;
+                carry = v >> 64;
+            }
+            let v = t[4] as u128 + carry;
+            t[3] = v as u64;
+            t[4] = t[5] + (v >> 64) as u64;
+        }
+
+        // the result is below 2n, so taking n away once is enough
+        let result = [t[0], t[1], t[2], t[3]];
+        let (reduced, borrow) = sub_limbs(&result, &N);
+        select((t[4] == 1) | !borrow, &reduced, &result)
+    }
+}
+
+impl Add for Scalar {
+    type Output = Self;
+
+    // the | merges the carry and borrow without branching on them
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, rhs: Self) -> Self {
+        // the sum is below 2n, which can be more than 2^256
+        let (sum, carry) = add_limbs(&self.0, &rhs.0);
+        let (reduced, borrow) = sub_limbs(&sum, &N);
+        Self(select(carry | !borrow, &reduced, &sum))
+    }
+}
+
+impl Mul for Scalar {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        // the first product has an extra 2^-256 in it, which multiplying by
+        // 2^512 in the second one cancels out
+        let product = Self::montgomery_multiply(&self.0, &rhs.0);
+        Self(Self::montgomery_multiply(&product, &R2))
+    }
+}
+
+impl Neg for Scalar {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let (negated, _) = sub_limbs(&N, &self.0);
+        // n - 0 would be n, which isn't below n
+        Self::conditional_select(&Self(negated), &Self::zero(), self.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Anything below n, with extra weight on the values just below n and the
+    /// small ones.
+    fn scalar_value() -> impl Strategy<Value = BigInt> {
+        prop_oneof![
+            any::<[u8; 32]>().prop_map(|bytes| BigInt::from_bytes_be(Sign::Plus, &bytes) % Secp256k1::n()),
+            any::<u64>().prop_map(|k| Secp256k1::n() - 1 - k),
+            any::<u64>().prop_map(BigInt::from),
+        ]
+    }
+
+    #[test]
+    fn test_from_bytes_be_reduces() {
+        let n = Secp256k1::n();
+        for value in [BigInt::from(0), BigInt::from(1), &n - 1, n.clone(), &n + 1, (BigInt::from(1) << 256) - 1] {
+            let actual = Scalar::from_bigint(&value);
+            assert_eq!(actual.to_bigint(), &value % &n, "{}", value);
+        }
+        assert_eq!(Scalar::from_bigint(&BigInt::from(-1)).to_bigint(), &n - 1);
+        assert_eq!(Scalar::from_bigint(&(BigInt::from(1) << 300)).to_bigint(), (BigInt::from(1) << 300) % &n);
+    }
+
+    #[test]
+    fn test_invert() {
+        // n - 1 is its own inverse
+        let minus_one = -Scalar::one();
+        assert_eq!(minus_one.invert(), minus_one);
+        assert_eq!(Scalar::zero().invert(), Scalar::zero());
+        assert_eq!(-Scalar::zero(), Scalar::zero());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_arithmetic_matches_bigint(a in scalar_value(), b in scalar_value()) {
+            let n = Secp256k1::n();
+            let sa = Scalar::from_bigint(&a);
+            let sb = Scalar::from_bigint(&b);
+
+            prop_assert_eq!((sa + sb).to_bigint(), (&a + &b) % &n);
+            prop_assert_eq!((sa * sb).to_bigint(), (&a * &b) % &n);
+            prop_assert_eq!((-sa).to_bigint(), (&n - &a) % &n);
+        }
+
+        #[test]
+        fn prop_invert_matches_bigint(a in scalar_value().prop_filter("zero has no inverse", |a| *a != BigInt::from(0))) {
+            let n = Secp256k1::n();
+            let sa = Scalar::from_bigint(&a);
+
+            prop_assert_eq!(sa.invert().to_bigint(), a.modpow(&(&n - 2), &n));
+            prop_assert_eq!(sa * sa.invert(), Scalar::one());
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use std::fmt;
+
+use num_bigint::{BigInt, RandBigInt, Sign};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::curve::{Curve, Secp256k1};
+use crate::field::Field;
+use crate::generator_table;
+use crate::point::{self, Point, PointError};
+use crate::scalar::Scalar;
+
+// https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki
+
+const AUX_TAG: &str = "BIP0340/aux";
+const NONCE_TAG: &str = "BIP0340/nonce";
+const CHALLENGE_TAG: &str = "BIP0340/challenge";
+
+/// A public key known only by its x coordinate. Of the two points with that
+/// x, it stands for the one with an even y.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct XOnlyPublicKey {
+    point: Point<Secp256k1>,
+}
+
+/// A BIP340 signature: the x coordinate of the nonce point R, and s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SchnorrSignature {
+    pub(crate) r: BigInt,
+    pub(crate) s: BigInt,
+}
+
+/// A rejected batch, with the position of every signature in it that fails
+/// on its own.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct BatchError {
+    pub(crate) invalid: Vec<usize>,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid signatures at {:?}", self.invalid)
+    }
+}
+
+impl XOnlyPublicKey {
+    /// lift_x from BIP340: the point with this x and an even y.
+    pub(crate) fn from_bytes(bytes: &[u8; 32]) -> Result<Self, PointError> {
+        let mut sec1 = vec![2];
+        sec1.extend(bytes);
+        Ok(Self { point: Point::from_sec1_bytes(&sec1)? })
+    }
+
+    /// Drops the y coordinate of a point. Returns `None` for infinity.
+    pub(crate) fn from_point(point: &Point<Secp256k1>) -> Option<Self> {
+        let point = if point.y_is_even() {
+            point.clone()
+        } else {
+            point.negate()
+        };
+        point.x().map(|_| Self { point })
+    }
+
+    pub(crate) fn to_bytes(&self) -> [u8; 32] {
+        self.point.x().unwrap().to_bytes()
+    }
+
+    /// The even y point the key stands for.
+    pub(crate) fn point(&self) -> &Point<Secp256k1> {
+        &self.point
+    }
+}
+
+impl SchnorrSignature {
+    pub(crate) fn from_bytes(bytes: &[u8; 64]) -> Self {
+        Self {
+            r: BigInt::from_bytes_be(Sign::Plus, &bytes[..32]),
+            s: BigInt::from_bytes_be(Sign::Plus, &bytes[32..]),
+        }
+    }
+
+    pub(crate) fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&int_to_bytes(&self.r));
+        bytes[32..].copy_from_slice(&int_to_bytes(&self.s));
+        bytes
+    }
+}
+
+/// SHA-256(SHA-256(tag) || SHA-256(tag) || data), so hashes made for one
+/// purpose can't be passed off as hashes for another.
+pub(crate) fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    for bytes in data {
+        hasher.update(bytes);
+    }
+    hasher.finalize().into()
+}
+
+/// Signs a message of any length. aux_rand should be fresh randomness, but
+/// the signature stays secure, just deterministic, if it isn't.
+/// Returns `None` if the private key isn't between 1 and n - 1.
+pub(crate) fn sign(private_key: &BigInt, message: &[u8], aux_rand: &[u8; 32]) -> Option<SchnorrSignature> {
+    let n = Secp256k1::n();
+    if *private_key < BigInt::from(1) || *private_key >= n {
+        return None
+    }
+
+    // the private key is negated if needed so that it matches the even y public key
+    let private_key = Scalar::from_bigint(private_key);
+    let public_point = generator_table::multiply_generator(&private_key);
+    let d = Scalar::conditional_select(&private_key, &-private_key, !public_point.y_is_even());
+    let public_key = XOnlyPublicKey::from_point(&public_point)?.to_bytes();
+
+    let aux_hash = tagged_hash(AUX_TAG, &[aux_rand]);
+    let mut t = d.to_bytes();
+    for (byte, aux_byte) in t.iter_mut().zip(aux_hash) {
+        *byte ^= aux_byte;
+    }
+    let rand = tagged_hash(NONCE_TAG, &[&t, &public_key, message]);
+    let k = Scalar::from_bytes_be(&rand);
+    if k.is_zero() {
+        return None
+    }
+
+    // likewise the nonce is negated so that R has an even y
+    let big_r = generator_table::multiply_generator(&k);
+    let k = Scalar::conditional_select(&k, &-k, !big_r.y_is_even());
+    let r = big_r.x()?.to_bytes();
+
+    let e = challenge(&r, &public_key, message);
+    let s = k + Scalar::from_bigint(&e) * d;
+
+    Some(SchnorrSignature { r: BigInt::from_bytes_be(Sign::Plus, &r), s: s.to_bigint() })
+}
+
+/// Checks a signature of a message against an x-only public key.
+pub(crate) fn verify(public_key: &XOnlyPublicKey, message: &[u8], signature: &SchnorrSignature) -> bool {
+    let n = Secp256k1::n();
+    if signature.r >= Secp256k1::p() || signature.s >= n {
+        return false
+    }
+
+    let e = challenge(&int_to_bytes(&signature.r), &public_key.to_bytes(), message);
+
+    // R = sG - eP
+    let big_r = generator_table::multiply_generator(&Scalar::from_bigint(&signature.s))
+        .add(public_key.point().clone().multiply(&(&n - e)));
+
+    match big_r.x() {
+        Some(x) => big_r.y_is_even() && x.to_bigint() == signature.r,
+        None => false,
+    }
+}
+
+/// Checks many signatures at once, much faster than one at a time. Gives
+/// the same answer as calling `verify` on each, and when the batch fails
+/// falls back to doing exactly that to find the bad ones.
+pub(crate) fn verify_batch(batch: &[(&XOnlyPublicKey, &[u8], &SchnorrSignature)]) -> Result<(), BatchError> {
+    verify_batch_with_rng(batch, &mut rand::thread_rng())
+}
+
+fn verify_batch_with_rng<R: Rng + ?Sized>(
+    batch: &[(&XOnlyPublicKey, &[u8], &SchnorrSignature)],
+    rng: &mut R,
+) -> Result<(), BatchError> {
+    // https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki#batch-verification
+    // Each signature satisfies sG = R + eP. Adding them up with random
+    // weights a gives (a1 s1 + a2 s2 + ...) G - a1 R1 - a1 e1 P1 - ... = 0,
+    // which a forger can only hit by guessing the weights.
+    let n = Secp256k1::n();
+    let one = BigInt::from(1);
+    let mut generator_scalar = BigInt::from(0);
+    let mut terms = Vec::with_capacity(2 * batch.len() + 1);
+
+    for (i, (public_key, message, signature)) in batch.iter().enumerate() {
+        if signature.s >= n {
+            return Err(find_invalid(batch))
+        }
+        // R is lifted with an even y, the same check `verify` makes
+        let Ok(big_r) = XOnlyPublicKey::from_bytes(&int_to_bytes(&signature.r)) else {
+            return Err(find_invalid(batch))
+        };
+        let e = challenge(&big_r.to_bytes(), &public_key.to_bytes(), message);
+
+        // the first weight can be 1 without weakening anything
+        let a = if i == 0 { one.clone() } else { rng.gen_bigint_range(&one, &n) };
+        generator_scalar += &a * &signature.s;
+        terms.push((big_r.point().clone(), -&a));
+        terms.push((public_key.point().clone(), -(a * e)));
+    }
+    terms.push((Secp256k1::generator(), generator_scalar));
+
+    if point::multi_scalar_multiply(&terms).is_infinity() {
+        Ok(())
+    } else {
+        Err(find_invalid(batch))
+    }
+}
+
+fn find_invalid(batch: &[(&XOnlyPublicKey, &[u8], &SchnorrSignature)]) -> BatchError {
+    let invalid = batch
+        .iter()
+        .enumerate()
+        .filter(|(_, (public_key, message, signature))| !verify(public_key, message, signature))
+        .map(|(i, _)| i)
+        .collect();
+    BatchError { invalid }
+}
+
+/// e = hash(R.x || P.x || m) mod n
+pub(crate) fn challenge(r: &[u8; 32], public_key: &[u8; 32], message: &[u8]) -> BigInt {
+    let hash = tagged_hash(CHALLENGE_TAG, &[r, public_key, message]);
+    BigInt::from_bytes_be(Sign::Plus, &hash) % Secp256k1::n()
+}
+
+/// A number below 2^256 as 32 big endian bytes.
+fn int_to_bytes(n: &BigInt) -> [u8; 32] {
+    let (_, bytes) = n.to_bytes_be();
+    let mut padded = [0u8; 32];
+    padded[32 - bytes.len()..].copy_from_slice(&bytes);
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use super::*;
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0340/test-vectors.csv
+    const TEST_VECTORS: &str = include_str!("test_vectors/bip340.csv");
+
+    struct TestVector {
+        index: usize,
+        secret_key: Option<BigInt>,
+        public_key: [u8; 32],
+        aux_rand: Option<[u8; 32]>,
+        message: Vec<u8>,
+        signature: [u8; 64],
+        valid: bool,
+    }
+
+    fn hex_to_vec(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn test_vectors() -> Vec<TestVector> {
+        TEST_VECTORS
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let fields: Vec<&str> = line.splitn(8, ',').collect();
+                TestVector {
+                    index: fields[0].parse().unwrap(),
+                    secret_key: BigInt::parse_bytes(fields[1].as_bytes(), 16),
+                    public_key: hex_to_vec(fields[2]).try_into().unwrap(),
+                    aux_rand: hex_to_vec(fields[3]).try_into().ok(),
+                    message: hex_to_vec(fields[4]),
+                    signature: hex_to_vec(fields[5]).try_into().unwrap(),
+                    valid: fields[6] == "TRUE",
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tagged_hash() {
+        let mut data = Sha256::digest(b"BIP0340/challenge").to_vec();
+        data.extend(data.clone());
+        data.extend(b"message");
+
+        assert_eq!(tagged_hash(CHALLENGE_TAG, &[b"mess", b"age"]), <[u8; 32]>::from(Sha256::digest(data)));
+    }
+
+    #[test]
+    fn test_bip340_sign_vectors() {
+        let vectors = test_vectors();
+        assert_eq!(vectors.len(), 19);
+
+        for vector in vectors {
+            let (Some(secret_key), Some(aux_rand)) = (&vector.secret_key, &vector.aux_rand) else {
+                continue
+            };
+
+            let public_point = Secp256k1::generator().multiply(secret_key);
+            let public_key = XOnlyPublicKey::from_point(&public_point).unwrap();
+            assert_eq!(public_key.to_bytes(), vector.public_key, "public key for index {}", vector.index);
+
+            let signature = sign(secret_key, &vector.message, aux_rand).unwrap();
+            assert_eq!(signature.to_bytes(), vector.signature, "signature for index {}", vector.index);
+        }
+    }
+
+    #[test]
+    fn test_bip340_verify_vectors() {
+        for vector in test_vectors() {
+            let signature = SchnorrSignature::from_bytes(&vector.signature);
+
+            let valid = XOnlyPublicKey::from_bytes(&vector.public_key)
+                .is_ok_and(|public_key| verify(&public_key, &vector.message, &signature));
+
+            assert_eq!(valid, vector.valid, "verification of index {}", vector.index);
+        }
+    }
+
+    #[test]
+    fn test_x_only_public_key() {
+        // G has an even y, so -G has an odd one and the x-only key stands for G
+        let point = Secp256k1::generator().negate();
+        let x_only = XOnlyPublicKey::from_point(&point).unwrap();
+
+        assert!(!point.y_is_even());
+        assert_eq!(x_only.point(), &point.negate());
+        assert_eq!(XOnlyPublicKey::from_bytes(&x_only.to_bytes()), Ok(x_only));
+        assert_eq!(XOnlyPublicKey::from_point(&Point::Infinity), None);
+    }
+
+    #[test]
+    fn test_sign_rejects_out_of_range_private_key() {
+        assert_eq!(sign(&BigInt::from(0), b"message", &[0; 32]), None);
+        assert_eq!(sign(&Secp256k1::n(), b"message", &[0; 32]), None);
+    }
+
+    /// The signing vectors plus a few more signatures, all valid.
+    fn valid_batch() -> Vec<(XOnlyPublicKey, Vec<u8>, SchnorrSignature)> {
+        let mut batch: Vec<_> = test_vectors()
+            .into_iter()
+            .filter(|vector| vector.valid)
+            .map(|vector| (
+                XOnlyPublicKey::from_bytes(&vector.public_key).unwrap(),
+                vector.message,
+                SchnorrSignature::from_bytes(&vector.signature),
+            ))
+            .collect();
+        for i in 1..8u8 {
+            let private_key = BigInt::from(i) * BigInt::from(0x1234_5678_9abcu64);
+            let public_key = XOnlyPublicKey::from_point(&Secp256k1::generator().multiply(&private_key)).unwrap();
+            let message = vec![i; i as usize];
+            let signature = sign(&private_key, &message, &[i; 32]).unwrap();
+            batch.push((public_key, message, signature));
+        }
+        batch
+    }
+
+    fn as_refs(batch: &[(XOnlyPublicKey, Vec<u8>, SchnorrSignature)]) -> Vec<(&XOnlyPublicKey, &[u8], &SchnorrSignature)> {
+        batch.iter().map(|(public_key, message, signature)| (public_key, &message[..], signature)).collect()
+    }
+
+    #[test]
+    fn test_verify_batch_accepts_valid_signatures() {
+        let batch = valid_batch();
+
+        assert_eq!(verify_batch(&as_refs(&batch)), Ok(()));
+        assert_eq!(verify_batch(&as_refs(&batch[..1])), Ok(()));
+        assert_eq!(verify_batch(&[]), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_batch_reports_each_invalid_vector() {
+        // every invalid BIP340 vector with a usable public key, hidden among valid ones
+        let valid = valid_batch();
+        for vector in test_vectors().into_iter().filter(|vector| !vector.valid) {
+            let Ok(public_key) = XOnlyPublicKey::from_bytes(&vector.public_key) else {
+                continue
+            };
+            let signature = SchnorrSignature::from_bytes(&vector.signature);
+            let mut batch = as_refs(&valid);
+            batch.insert(3, (&public_key, &vector.message, &signature));
+
+            assert_eq!(verify_batch(&batch), Err(BatchError { invalid: vec![3] }), "index {}", vector.index);
+        }
+    }
+
+    #[test]
+    fn test_verify_batch_matches_individual_verification() {
+        let mut batch = valid_batch();
+        batch[0].1.push(0);
+        batch[5].2.s += 1;
+        let (first_key, _, _) = batch[2].clone();
+        batch[9].0 = first_key;
+
+        let expected: Vec<usize> = batch
+            .iter()
+            .enumerate()
+            .filter(|(_, (public_key, message, signature))| !verify(public_key, message, signature))
+            .map(|(i, _)| i)
+            .collect();
+
+        assert_eq!(expected, vec![0, 5, 9]);
+        assert_eq!(verify_batch(&as_refs(&batch)), Err(BatchError { invalid: expected }));
+    }
+
+    #[test]
+    fn test_verify_batch_weights_catch_cancelling_errors() {
+        // s1 + 1 and s2 - 1 would cancel out in a plain sum, but not with random weights
+        let mut batch = valid_batch();
+        batch[1].2.s += 1;
+        batch[2].2.s -= 1;
+
+        assert_eq!(verify_batch(&as_refs(&batch)), Err(BatchError { invalid: vec![1, 2] }));
+    }
+
+    /// Compares batch verification with checking each signature on its own.
+    /// Run with `cargo test --release -- --ignored --nocapture bench`
+    #[test]
+    #[ignore]
+    fn bench_verify_batch() {
+        let batch: Vec<_> = (1..=100u32)
+            .map(|i| {
+                let private_key = BigInt::from(i) * BigInt::from(0x1234_5678_9abcu64);
+                let public_key = XOnlyPublicKey::from_point(&Secp256k1::generator().multiply(&private_key)).unwrap();
+                let message = i.to_be_bytes().to_vec();
+                let signature = sign(&private_key, &message, &[0; 32]).unwrap();
+                (public_key, message, signature)
+            })
+            .collect();
+        let batch = as_refs(&batch);
+
+        let start = std::time::Instant::now();
+        assert!(batch.iter().all(|(public_key, message, signature)| verify(public_key, message, signature)));
+        println!("verify x {}:       {:?}", batch.len(), start.elapsed());
+
+        let start = std::time::Instant::now();
+        assert_eq!(verify_batch(&batch), Ok(()));
+        println!("verify_batch x {}: {:?}", batch.len(), start.elapsed());
+    }
+}
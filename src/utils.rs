@@ -1,13 +1,36 @@
-#![feature(const_option)]
-
-use num_bigint::{BigInt, BigUint};
-
-// static MAX_BIGUINT: BigUint = BigUint::from_bytes_be(&vec![0xFFu8;32][..]);
-
-pub(crate) const MAX_BIGUINT: &[u8; 64] = b"FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF";
+use num_bigint::BigInt;
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
 
 pub(crate) const N: &[u8; 64] = b"FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141";
 
 pub(crate) fn to_bigint(num: &[u8; 64]) -> BigInt {
     BigInt::parse_bytes(num, 16).unwrap()
 }
+
+/// Formats bytes as an upper case hex string.
+pub(crate) fn to_hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+/// Parses a hex string of either case, returning `None` if it has an odd
+/// length or anything that isn't a hex digit.
+pub(crate) fn from_hex_string(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// SHA-256 applied twice, used for checksums and message hashes.
+pub(crate) fn double_sha256(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(bytes)).into()
+}
+
+/// RIPEMD-160 of the SHA-256, the hash in P2PKH addresses.
+pub(crate) fn hash160(bytes: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(bytes)).into()
+}
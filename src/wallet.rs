@@ -1,155 +1,248 @@
-use std::fmt::format;
+use std::fmt;
 
 use num_bigint::{BigInt, Sign};
-use sha2::Sha256;
-use sha2::Digest as ShaDigest;
-use ripemd::Ripemd160;
-use ripemd::Digest as RipDigest;
 
+use crate::address::Address;
+use crate::curve::{Curve, Secp256k1};
+use crate::ecdsa::{self, Signature};
+use crate::generator_table;
+use crate::message;
+use crate::network::Network;
 use crate::point::Point;
-use crate::base58::Base58;
+use crate::scalar::Scalar;
+use crate::schnorr::{self, SchnorrSignature, XOnlyPublicKey};
+use crate::base58::{Base58, Base58Check, Base58Error};
+use crate::utils;
 
 // https://gobittest.appspot.com/
 
-struct Wallet {
-    private_key: BigInt,
-    public_key_point: Point,
-    a: BigInt,
-    b: BigInt,
-    p: BigInt,
-    n: BigInt,
+/// Follows the 32 key bytes in the WIF of a key whose public key is compressed.
+const WIF_COMPRESSED_SUFFIX: u8 = 0x01;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum WalletError {
+    /// The private key given as a string isn't hex.
+    InvalidHex,
+    /// The key is zero, or n or more.
+    PrivateKeyOutOfRange,
+    /// Segwit only allows compressed public keys.
+    UncompressedPublicKey,
 }
 
-impl From<BigInt> for Wallet {
-    fn from(private_key: BigInt) -> Self {
-        let two = BigInt::from(2);
-        let p = two.pow(256) 
-            - two.pow(32) 
-            - two.pow(9) 
-            - two.pow(8) 
-            - two.pow(7)
-            - two.pow(6)
-            - two.pow(4)
-            - BigInt::from(1u8);
-        let a = BigInt::from(0);
-        let n = BigInt::from_bytes_be(Sign::Plus, b"FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141");
-
-
-        let public_key_point = Wallet::generator_point().multiply(&private_key, &p, &a);
-        // let public_key_point = private_key.clone() * Wallet::generator_point().multiply();
-
-        Self { 
-            private_key,
-            public_key_point,
-            a,
-            b: BigInt::from(7),
-            p,
-            n,
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidHex => write!(f, "the private key isn't hex"),
+            Self::PrivateKeyOutOfRange => write!(f, "the private key has to be between 1 and n - 1"),
+            Self::UncompressedPublicKey => write!(f, "segwit doesn't allow uncompressed public keys"),
         }
     }
 }
 
-impl From<String> for Wallet {
-    fn from(private_key: String) -> Self {
-        let two = BigInt::from(2);
-        let p = two.pow(256) 
-            - two.pow(32) 
-            - two.pow(9) 
-            - two.pow(8) 
-            - two.pow(7)
-            - two.pow(6)
-            - two.pow(4)
-            - BigInt::from(1u8);
-        let a = BigInt::from(0);
-        let n = BigInt::from_bytes_be(Sign::Plus, b"FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141");
-        let private_key = BigInt::parse_bytes(private_key.as_bytes(), 16).unwrap();
-        let public_key_point = Wallet::generator_point().multiply(&private_key, &p, &a);
-
-        Self { 
-            private_key,
-            public_key_point,
-            a,
-            b: BigInt::from(7),
-            p,
-            n,
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum WifError {
+    InvalidCharacter { character: char, position: usize },
+    /// A WIF key decodes to 37 bytes, or 38 with the compressed suffix.
+    InvalidLength,
+    InvalidChecksum,
+    /// Neither the mainnet nor the testnet version byte.
+    InvalidVersion(u8),
+    /// The 38th byte has to be 0x01.
+    InvalidCompressedSuffix(u8),
+    Wallet(WalletError),
+}
+
+impl fmt::Display for WifError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidCharacter { character, position } => write!(f, "'{}' at position {} isn't a base58 character", character, position),
+            Self::InvalidLength => write!(f, "a WIF key decodes to 37 or 38 bytes"),
+            Self::InvalidChecksum => write!(f, "the checksum doesn't match"),
+            Self::InvalidVersion(version) => write!(f, "{:#04x} isn't a WIF version byte", version),
+            Self::InvalidCompressedSuffix(suffix) => write!(f, "expected the compressed suffix 0x01 but found {:#04x}", suffix),
+            Self::Wallet(error) => write!(f, "{}", error),
         }
     }
 }
 
+impl From<WalletError> for WifError {
+    fn from(error: WalletError) -> Self {
+        Self::Wallet(error)
+    }
+}
+
+pub(crate) struct Wallet {
+    private_key: BigInt,
+    public_key_point: Point<Secp256k1>,
+    network: Network,
+    /// Whether the compressed form of the public key is the one in use.
+    compressed: bool,
+}
+
+impl TryFrom<BigInt> for Wallet {
+    type Error = WalletError;
+
+    /// A mainnet wallet using the compressed public key. `Wallet::new` takes
+    /// the network and key form for any other kind.
+    fn try_from(private_key: BigInt) -> Result<Self, Self::Error> {
+        Self::new(private_key, Network::Mainnet, true)
+    }
+}
+
+impl TryFrom<String> for Wallet {
+    type Error = WalletError;
+
+    /// Like `TryFrom<BigInt>`, from the key in hex.
+    fn try_from(private_key: String) -> Result<Self, Self::Error> {
+        let private_key = BigInt::parse_bytes(private_key.as_bytes(), 16).ok_or(WalletError::InvalidHex)?;
+        Self::try_from(private_key)
+    }
+}
+
 impl Wallet {
-    fn generator_point() -> Point {
-            Point::from_xy(
-                BigInt::parse_bytes(b"79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798", 16).unwrap(), 
-                BigInt::parse_bytes(b"483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8", 16).unwrap()
-            )
+    /// A wallet for the network's addresses and WIF, using the compressed or
+    /// uncompressed form of the public key. Every other constructor goes
+    /// through here, so a wallet never holds a key whose public key is the
+    /// point at infinity.
+    pub(crate) fn new(private_key: BigInt, network: Network, compressed: bool) -> Result<Self, WalletError> {
+        if private_key < BigInt::from(1) || private_key >= Secp256k1::n() {
+            return Err(WalletError::PrivateKeyOutOfRange)
+        }
+        let public_key_point = generator_table::multiply_generator(&Scalar::from_bigint(&private_key));
+
+        Ok(Self {
+            private_key,
+            public_key_point,
+            network,
+            compressed,
+        })
+    }
+
+    /// Imports a private key in Wallet Import Format, which also says the
+    /// network and whether the public key is compressed.
+    pub(crate) fn from_wif(wif: &str) -> Result<Self, WifError> {
+        // https://en.bitcoin.it/wiki/Wallet_import_format
+        let Base58Check { version, payload } = Base58::check_decode(wif).map_err(|error| match error {
+            Base58Error::InvalidCharacter { character, position } => WifError::InvalidCharacter { character, position },
+            Base58Error::TooShort(_) => WifError::InvalidLength,
+            Base58Error::InvalidChecksum => WifError::InvalidChecksum,
+        })?;
+        if payload.len() != 32 && payload.len() != 33 {
+            return Err(WifError::InvalidLength)
+        }
+
+        // Signet and regtest keys share testnet's version byte, so they come
+        // back as testnet keys.
+        let network = Network::from_wif_version(version).ok_or(WifError::InvalidVersion(version))?;
+        let compressed = match payload.get(32) {
+            None => false,
+            Some(&WIF_COMPRESSED_SUFFIX) => true,
+            Some(&suffix) => return Err(WifError::InvalidCompressedSuffix(suffix)),
+        };
+
+        let private_key = BigInt::from_bytes_be(Sign::Plus, &payload[..32]);
+        Ok(Self::new(private_key, network, compressed)?)
     }
 
-    fn private_key(&self) -> String {
-        self.private_key.to_str_radix(16).to_uppercase().to_string()
+    pub(crate) fn network(&self) -> Network {
+        self.network
     }
 
-    fn decoded_private_key(&self) -> String {
+    pub(crate) fn private_key(&self) -> String {
+        format!("{:0>64}", self.private_key.to_str_radix(16).to_uppercase())
+    }
+
+    pub(crate) fn decoded_private_key(&self) -> String {
         self.private_key.to_str_radix(10).to_uppercase().to_string()
     }
 
-    fn wif_encoded_private_key(&self) -> String {
-        
+    /// The private key in Wallet Import Format: the network's version byte,
+    /// the 32 byte key, 0x01 if the public key is compressed, and a checksum,
+    /// all in base58.
+    pub(crate) fn wif_encoded_private_key(&self) -> String {
+        // https://en.bitcoin.it/wiki/Wallet_import_format
+        let (_, key) = self.private_key.to_bytes_be();
+        let mut payload = vec![0; 32 - key.len()];
+        payload.extend(key);
+        if self.compressed {
+            payload.push(WIF_COMPRESSED_SUFFIX);
+        }
+
+        Base58::check_encode(self.network.wif_version(), &payload)
     }
 
-    fn pub_key_compressed_string(&self) -> String {
+    pub(crate) fn pub_key_compressed_string(&self) -> String {
         // https://learnmeabitcoin.com/technical/public-key
-        let prefix = if self.public_key_point.y_is_even() {
-            "02".to_string()
-        } else {
-            "03".to_string()
-        };
-        format!("{}{}", prefix, self.public_key_point.x_to_hex_string())
+        utils::to_hex_string(&self.pub_key_compressed_bytes())
     }
 
-    fn pub_key_uncompressed_string(&self) -> String {
-        format!("04{}{}", self.public_key_point.x_to_hex_string(), self.public_key_point.y_to_hex_string())
+    pub(crate) fn pub_key_uncompressed_string(&self) -> String {
+        utils::to_hex_string(&self.pub_key_uncompressed_bytes())
     }
 
     fn pub_key_compressed_bytes(&self) -> Vec<u8> {
         // https://learnmeabitcoin.com/technical/public-key
+        self.public_key_point.to_sec1_bytes(true)
+    }
 
-        let mut thing = if self.public_key_point.y_is_even() {
-            Vec::from([2])
-        } else {
-            Vec::from([3])
-        };
-        thing.append(&mut self.public_key_point.x_bytes());
-        thing
+    pub(crate) fn pub_key_uncompressed_bytes(&self) -> Vec<u8> {
+        self.public_key_point.to_sec1_bytes(false)
     }
 
-    fn pub_key_uncompressed_bytes(&self) -> Vec<u8> {
-        let mut thing = Vec::from([4]);
-        thing.append(&mut self.public_key_point.x_bytes());
-        thing.append(&mut self.public_key_point.y_bytes());
-        thing
+    /// The SEC public key in whichever form the wallet uses.
+    fn pub_key_bytes(&self) -> Vec<u8> {
+        self.public_key_point.to_sec1_bytes(self.compressed)
     }
 
-    fn address(&self) -> String {
+    /// The 32 byte x-only key BIP340 and Taproot use.
+    pub(crate) fn pub_key_x_only_bytes(&self) -> Vec<u8> {
+        self.x_only_public_key().to_bytes().to_vec()
+    }
 
-        let private_key = BigInt::parse_bytes(b"038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14776", 16).unwrap();
-        let wallet = Wallet::from(private_key);
+    pub(crate) fn x_only_public_key(&self) -> XOnlyPublicKey {
+        // `new` keeps the key in range, so the point is never at infinity
+        XOnlyPublicKey::from_point(&self.public_key_point).unwrap()
+    }
 
-        // let public_address = wallet.pub_key_compressed_bytes();
+    pub(crate) fn sign(&self, z: &[u8; 32]) -> Signature {
+        // `new` keeps the key in range
+        ecdsa::sign(&self.private_key, z).unwrap()
+    }
 
-        let public_key = wallet.pub_key_compressed_bytes();
-        let mut sha_hasher = Sha256::new();
-        sha_hasher.update(public_key);
-        let sha_result = sha_hasher.finalize();
-        let mut rip_hasher = Ripemd160::new();
-        rip_hasher.update(&sha_result[..]);
-        let rip_result = rip_hasher.finalize();
+    pub(crate) fn sign_schnorr(&self, message: &[u8], aux_rand: &[u8; 32]) -> SchnorrSignature {
+        // the key is in range, and a nonce of zero would take breaking SHA256
+        schnorr::sign(&self.private_key, message, aux_rand).unwrap()
+    }
 
-        let b58 = Base58::from_bigint(BigInt::from_bytes_be(Sign::Plus, &rip_result[..]));
+    /// Signs a message for Bitcoin Core's verifymessage, for the address of
+    /// the public key in the form the wallet uses.
+    pub(crate) fn sign_message(&self, message: &str) -> String {
+        message::sign_message(&self.private_key, self.compressed, message).unwrap()
+    }
 
-        dbg!(sha_result);
-        dbg!(rip_result);
+    /// The P2PKH address: the network's version byte and the Hash160 of the
+    /// public key, in Base58Check. The compressed and uncompressed forms of
+    /// the same key give different addresses.
+    pub(crate) fn address(&self) -> String {
+        // https://learnmeabitcoin.com/technical/public-key-hash
+        let public_key_hash = utils::hash160(&self.pub_key_bytes());
+        Base58::check_encode(self.network.p2pkh_version(), &public_key_hash)
+    }
 
-        "hello".to_string()
+    /// The native segwit P2WPKH address: witness version 0 and the Hash160 of
+    /// the compressed public key, in bech32 with the network's HRP. A wallet
+    /// using the uncompressed key can't have one, since segwit treats those
+    /// as non-standard.
+    /// There's no witness version to choose: P2WPKH only exists under
+    /// version 0. Version 1 is Taproot, which pays to an x-only key rather
+    /// than a key hash, and the later versions have no meaning yet.
+    pub(crate) fn p2wpkh_address(&self) -> Result<String, WalletError> {
+        // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#restrictions-on-public-key-type
+        if !self.compressed {
+            return Err(WalletError::UncompressedPublicKey)
+        }
+        let public_key = self.pub_key_compressed_bytes().try_into().unwrap();
+        Ok(Address::p2wpkh(&public_key, self.network).to_string())
     }
 
     // fn private_key_base58(&self) {
@@ -161,28 +254,16 @@ impl Wallet {
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
-
-    // #[test]
-    // fn test_start_public_key_calculations() {
-    //     let private_key = BigInt::parse_bytes(b"1E99423A4ED27608A15A2616A2B0E9E52CED330AC530EDCC32C8FFC6A526AEDD", 16).unwrap();
-
-    //     let point = Point::generator_point();
-
-    //     // K = kG
-    //     // public_key = private_key * generater_point
-    //     let public_key = point * private_key;
-
-    //     let expected_x = "F028892BAD7ED57D2FB57BF33081D5CFCF6F9ED3D3D7F159C2E2FFF579DC341A".to_string();
-    //     // let expected_y = "07CF33DA18BD734C600B96A72BBC4749D5141C90EC8AC328AE52DDFE2E505BDB".to_string();
-
-    //     assert_eq!(public_key.x_to_hex_string(), expected_x);
-    //     // assert_eq!(public_key.y_to_hex_string(), expected_y);
-    // }
+    use ripemd::Ripemd160;
+    use sha2::{Digest, Sha256};
+    use std::str::FromStr;
+    use crate::address::AddressType;
+    use crate::bech32::{Bech32, Variant};
 
     #[test]
     fn test_generate_compressed_public_key_even() {
         let private_key = "038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14776".to_string();
-        let wallet = Wallet::from(private_key);
+        let wallet = Wallet::try_from(private_key).unwrap();
 
         let public_key = wallet.pub_key_compressed_string();
 
@@ -194,7 +275,7 @@ mod tests {
     #[test]
     fn test_generate_compressed_public_key_odd() {
         let private_key = "038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14777".to_string();
-        let wallet = Wallet::from(private_key);
+        let wallet = Wallet::try_from(private_key).unwrap();
 
         let public_key = wallet.pub_key_compressed_string();
 
@@ -206,7 +287,7 @@ mod tests {
     #[test]
     fn test_generate_uncompressed_public_key() {
         let private_key = "038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14776".to_string();
-        let wallet = Wallet::from(private_key);
+        let wallet = Wallet::try_from(private_key).unwrap();
 
         let public_key = wallet.pub_key_uncompressed_string();
 
@@ -218,7 +299,7 @@ mod tests {
     #[test]
     fn test_p69_follow_along() {
         let prvate_key = "038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14776".to_string();
-        let wallet = Wallet::from(prvate_key);
+        let wallet = Wallet::try_from(prvate_key).unwrap();
 
         let public_key = wallet.pub_key_compressed_string();
         let expected_pub_key = "0202a406624211f2abbdc68da3df929f938c3399dd79fac1b51b0e4ad1d26a47aa".to_uppercase().to_string();
@@ -226,7 +307,7 @@ mod tests {
         assert_eq!(public_key, expected_pub_key);
 
         let mut sha_hasher = Sha256::new();
-        sha_hasher.update(public_key);
+        sha_hasher.update(wallet.pub_key_compressed_bytes());
         let sha_result = sha_hasher.finalize();
         // dbg!("Public key shaed 1 time:", sha_result);
 
@@ -235,48 +316,188 @@ mod tests {
         let rip_result = rip_hasher.finalize();
         // dbg!("RIPEMD160ed:", &rip_result);
 
-        let mut added_00_byte = Vec::from([0]);
-        added_00_byte.append(&mut rip_result.to_vec());
-        // dbg!("zeros added:", &added_00_byte);
+        let base58_address = Base58::check_encode(Network::Mainnet.p2pkh_version(), &rip_result[..]);
+        let expected_address = "1PRTTaJesdNovgne6Ehcdu1fpEdX7913CK".to_string();
 
-        let mut sha_hasher = Sha256::new();
-        sha_hasher.update(added_00_byte.clone());
-        let sha_result = sha_hasher.finalize();
-        // dbg!("Added zeros hashed once", &sha_result);
+        assert_eq!(base58_address, expected_address);
+        assert_eq!(wallet.address(), expected_address);
+    }
 
-        let mut sha_hasher = Sha256::new();
-        sha_hasher.update(&sha_result[..]);
-        let sha_result = sha_hasher.finalize();
-        // dbg!("Added zeros hashed twice", sha_result);
+    #[test]
+    fn test_p76_follow_along() {
+        let prvate_key = "3aba4162c7251c891207b747840551a71939b0de081f85c4e44cf7c13e41daa6".to_string();
+        let wallet = Wallet::try_from(prvate_key).unwrap();
+
+        let private_key = "3aba4162c7251c891207b747840551a71939b0de081f85c4e44cf7c13e41daa6".to_string();
+        assert_eq!(wallet.private_key().to_lowercase(), private_key);
 
-        let first_four_bytes = &sha_result[0..4];
+        let decoded_private_key = "26563230048437957592232553826663696440606756685920117476832299673293013768870".to_string();
+        assert_eq!(wallet.decoded_private_key().to_lowercase(), decoded_private_key);
 
-        added_00_byte.append(&mut first_four_bytes.to_vec());
-        // dbg!("stuff with checksum:", &added_00_byte);
 
-        let base58_address = Base58::from_vec_u8(added_00_byte);
-        let expected_address = "1PRTTaJesdNovgne6Ehcdu1fpEdX7913CK".to_string();
+    }
 
-        assert_eq!(base58_address, expected_address);
+    #[test]
+    fn test_wif_encoded_private_key() {
+        let private_key = BigInt::parse_bytes(b"3aba4162c7251c891207b747840551a71939b0de081f85c4e44cf7c13e41daa6", 16).unwrap();
+        let cases = [
+            (Network::Mainnet, false, "5JG9hT3beGTJuUAmCQEmNaxAuMacCTfXuw1R3FCXig23RQHMr4K"),
+            (Network::Mainnet, true, "KyBsPXxTuVD82av65KZkrGrWi5qLMah5SdNq6uftawDbgKa2wv6S"),
+            (Network::Testnet, false, "922nHBs9EVXSsXg3pk8gFBW8Z1wKMdCjFssN7sZ34Qm6CLpzuyJ"),
+            (Network::Testnet, true, "cPYrrSxKLYuPC2PMTjNtDbMaLK8k22nmWfXJDL8Q63sbw4ihZGFM"),
+            (Network::Signet, false, "922nHBs9EVXSsXg3pk8gFBW8Z1wKMdCjFssN7sZ34Qm6CLpzuyJ"),
+            (Network::Regtest, true, "cPYrrSxKLYuPC2PMTjNtDbMaLK8k22nmWfXJDL8Q63sbw4ihZGFM"),
+        ];
+
+        for (network, compressed, expected) in cases {
+            let wallet = Wallet::new(private_key.clone(), network, compressed).unwrap();
+
+            assert_eq!(wallet.wif_encoded_private_key(), expected);
+        }
+    }
+
+    #[test]
+    fn test_wif_encoded_private_key_pads_small_keys() {
+        let wallet = Wallet::try_from(BigInt::from(1)).unwrap();
+
+        assert_eq!(wallet.wif_encoded_private_key(), "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn");
+    }
 
+    #[test]
+    fn test_from_wif() {
+        // https://github.com/bitcoin/bitcoin/blob/master/test/functional/rpc_signmessage.py
+        let wallet = Wallet::from_wif("cUeKHd5orzT3mz8P9pxyREHfsWtVfgsfDjiZZBcjUBAaGk1BTj7N").unwrap();
 
-        // let b58 = Base58::from_bigint(BigInt::from_bytes_be(Sign::Plus, &added_00_byte[..]));
+        assert_eq!(wallet.private_key(), "D2B8A0116D641FE7D3036F8464628FB595B480414C13A301B3D4038C811C28B0");
+        assert_eq!(wallet.network, Network::Testnet);
+        assert!(wallet.compressed);
 
+        let wallet = Wallet::from_wif("5JG9hT3beGTJuUAmCQEmNaxAuMacCTfXuw1R3FCXig23RQHMr4K").unwrap();
 
+        assert_eq!(wallet.private_key(), "3ABA4162C7251C891207B747840551A71939B0DE081F85C4E44CF7C13E41DAA6");
+        assert_eq!(wallet.network, Network::Mainnet);
+        assert!(!wallet.compressed);
     }
 
     #[test]
-    fn test_p76_follow_along() {
-        let prvate_key = "3aba4162c7251c891207b747840551a71939b0de081f85c4e44cf7c13e41daa6".to_string();
-        let wallet = Wallet::from(prvate_key);
+    fn test_new_rejects_out_of_range_keys() {
+        for private_key in [BigInt::from(0), BigInt::from(-1), Secp256k1::n()] {
+            assert_eq!(Wallet::new(private_key.clone(), Network::Mainnet, true).err(), Some(WalletError::PrivateKeyOutOfRange));
+            assert_eq!(Wallet::try_from(private_key).err(), Some(WalletError::PrivateKeyOutOfRange));
+        }
+        assert!(Wallet::new(Secp256k1::n() - 1, Network::Mainnet, true).is_ok());
+    }
 
-        let private_key = "3aba4162c7251c891207b747840551a71939b0de081f85c4e44cf7c13e41daa6".to_string();
-        assert_eq!(wallet.private_key().to_lowercase(), private_key);
+    #[test]
+    fn test_wif_round_trip() {
+        for network in [Network::Mainnet, Network::Testnet] {
+            for compressed in [false, true] {
+                let wallet = Wallet::new(BigInt::from(0x1234_5678u64), network, compressed).unwrap();
 
-        let decoded_private_key = "26563230048437957592232553826663696440606756685920117476832299673293013768870".to_string();
-        assert_eq!(wallet.decoded_private_key().to_lowercase(), decoded_private_key);
+                let imported = Wallet::from_wif(&wallet.wif_encoded_private_key()).unwrap();
+
+                assert_eq!(imported.private_key, wallet.private_key);
+                assert_eq!(imported.network, network);
+                assert_eq!(imported.compressed, compressed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_wif_rejects_malformed_keys() {
+        let wif = "KyBsPXxTuVD82av65KZkrGrWi5qLMah5SdNq6uftawDbgKa2wv6S";
+        let mut bad_checksum = wif.to_string();
+        bad_checksum.replace_range(51.., "T");
+        let bad_version = Base58::check_encode(0x81, &[0x11; 32]);
+        let bad_suffix = Base58::check_encode(Network::Mainnet.wif_version(), &[&[0x11; 32][..], &[0x02]].concat());
+        let too_short = Base58::check_encode(Network::Mainnet.wif_version(), &[0x11; 31]);
+        let too_long = Base58::check_encode(Network::Mainnet.wif_version(), &[&[0x11; 32][..], &[0x01, 0x01]].concat());
+        let zero_key = Base58::check_encode(Network::Mainnet.wif_version(), &[0; 32]);
+        let big_key = Base58::check_encode(Network::Mainnet.wif_version(), &[0xff; 32]);
+
+        assert_eq!(Wallet::from_wif("KyBsPXxTuVD82av65KZkrGrWi5qLMah5SdNq6uftawDbgKa2wv0S").err(), Some(WifError::InvalidCharacter { character: '0', position: 50 }));
+        assert_eq!(Wallet::from_wif(&bad_checksum).err(), Some(WifError::InvalidChecksum));
+        assert_eq!(Wallet::from_wif(&wif[..40]).err(), Some(WifError::InvalidChecksum));
+        assert_eq!(Wallet::from_wif(&too_short).err(), Some(WifError::InvalidLength));
+        assert_eq!(Wallet::from_wif(&bad_version).err(), Some(WifError::InvalidVersion(0x81)));
+        assert_eq!(Wallet::from_wif(&bad_suffix).err(), Some(WifError::InvalidCompressedSuffix(0x02)));
+        assert_eq!(Wallet::from_wif(&too_long).err(), Some(WifError::InvalidLength));
+        assert_eq!(Wallet::from_wif(&zero_key).err(), Some(WifError::Wallet(WalletError::PrivateKeyOutOfRange)));
+        assert_eq!(Wallet::from_wif(&big_key).err(), Some(WifError::Wallet(WalletError::PrivateKeyOutOfRange)));
+    }
+
+    #[test]
+    fn test_try_from_rejects_non_hex_keys() {
+        assert_eq!(Wallet::try_from("not a key".to_string()).err(), Some(WalletError::InvalidHex));
+        assert_eq!(Wallet::try_from("3aba4162c7251c891207b747840551a71939b0de081f85c4e44cf7c13e41daaz".to_string()).err(), Some(WalletError::InvalidHex));
+        assert_eq!(Wallet::try_from(String::new()).err(), Some(WalletError::InvalidHex));
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let private_key = "038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14776".to_string();
+        let wallet = Wallet::try_from(private_key).unwrap();
+        let z: [u8; 32] = Sha256::digest(b"Mastering Bitcoin").into();
+
+        let signature = wallet.sign(&z);
+
+        assert!(ecdsa::verify(&wallet.public_key_point, &z, &signature));
+
+        let other_wallet = Wallet::try_from(BigInt::from(12345)).unwrap();
+        assert!(!ecdsa::verify(&other_wallet.public_key_point, &z, &signature));
+    }
+
+    #[test]
+    fn test_schnorr_sign_and_verify() {
+        // BIP340 test vector 1
+        let wallet = Wallet::try_from("B7E151628AED2A6ABF7158809CF4F3C762E7160F38B4DA56A784D9045190CFEF".to_string()).unwrap();
+        let message = "Mastering Bitcoin".as_bytes();
+
+        let signature = wallet.sign_schnorr(message, &[7; 32]);
+
+        assert_eq!(utils::to_hex_string(&wallet.pub_key_x_only_bytes()), "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659");
+        assert_eq!(wallet.pub_key_x_only_bytes(), wallet.pub_key_compressed_bytes()[1..]);
+        assert!(schnorr::verify(&wallet.x_only_public_key(), message, &signature));
+        assert!(!schnorr::verify(&wallet.x_only_public_key(), b"Mastering Bitcoin!", &signature));
+    }
+
+    #[test]
+    fn test_sign_message() {
+        // https://github.com/bitcoin/bitcoin/blob/master/test/functional/rpc_signmessage.py
+        let wallet = Wallet::from_wif("cUeKHd5orzT3mz8P9pxyREHfsWtVfgsfDjiZZBcjUBAaGk1BTj7N").unwrap();
+        let message = "This is just a test message";
+
+        let signature = wallet.sign_message(message);
+
+        assert_eq!(signature, "INbVnW4e6PeRmsv2Qgu8NuopvrVjkcxob+sX8OcZG0SALhWybUjzMLPdAsXI46YZGb0KQTRii+wWIQzRpG/U+S0=");
+        assert_eq!(message::verify_message(&wallet.address(), &signature, message), Ok(true));
+    }
+
+    #[test]
+    fn test_sign_message_uncompressed() {
+        let wallet = Wallet::new(BigInt::from(0x1234_5678u64), Network::Mainnet, false).unwrap();
 
+        let signature = wallet.sign_message("Mastering Bitcoin");
 
+        assert_eq!(message::verify_message(&wallet.address(), &signature, "Mastering Bitcoin"), Ok(true));
+    }
+
+    #[test]
+    fn test_normalized_high_s_signature_still_verifies() {
+        let private_key = "038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14776".to_string();
+        let wallet = Wallet::try_from(private_key).unwrap();
+        let z: [u8; 32] = Sha256::digest(b"Mastering Bitcoin").into();
+        let low = wallet.sign(&z);
+        let high = Signature { r: low.r.clone(), s: Secp256k1::n() - &low.s };
+
+        assert!(ecdsa::verify(&wallet.public_key_point, &z, &high));
+        assert!(!ecdsa::verify_low_s(&wallet.public_key_point, &z, &high));
+
+        let normalized = high.normalize_s();
+
+        assert_eq!(normalized, low);
+        assert!(ecdsa::verify(&wallet.public_key_point, &z, &normalized));
+        assert!(ecdsa::verify_low_s(&wallet.public_key_point, &z, &normalized));
     }
 
     #[test]
@@ -284,7 +505,7 @@ mod tests {
         // https://gobittest.appspot.com/Address
         let private_key = BigInt::parse_bytes(b"038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14776", 16).unwrap();
         // dbg!("Private key:", &private_key);
-        let wallet = Wallet::from(private_key);
+        let wallet = Wallet::new(private_key, Network::Mainnet, false).unwrap();
 
         // let public_address = wallet.pub_key_compressed_bytes();
 
@@ -301,33 +522,105 @@ mod tests {
         let rip_result = rip_hasher.finalize();
         // dbg!("RIPEMD160ed:", &rip_result);
 
-        let mut added_00_byte = Vec::from([0]);
-        added_00_byte.append(&mut rip_result.to_vec());
-        // dbg!("zeros added:", &added_00_byte);
+        let b58 = Base58::check_encode(Network::Mainnet.p2pkh_version(), &rip_result[..]);
 
-        let mut sha_hasher = Sha256::new();
-        sha_hasher.update(added_00_byte.clone());
-        let sha_result = sha_hasher.finalize();
-        // dbg!("Added zeros hashed once", &sha_result);
+        // dbg!(b58);
+        // dbg!(rip_result);
 
-        let mut sha_hasher = Sha256::new();
-        sha_hasher.update(&sha_result[..]);
-        let sha_result = sha_hasher.finalize();
-        // dbg!("Added zeros hashed twice", sha_result);
+        let expected_b58 = "14K1y4Epb341duzDmWsPniLyBh9EVh8jG3".to_string();
 
-        let first_four_bytes = &sha_result[0..4];
+        assert_eq!(b58, expected_b58);
+        assert_eq!(wallet.address(), expected_b58);
+    }
 
-        added_00_byte.append(&mut first_four_bytes.to_vec());
-        // dbg!("stuff with checksum:", &added_00_byte);
+    #[test]
+    fn test_address() {
+        let private_key = BigInt::parse_bytes(b"038109007313a5807b2eccc082c8c3fbb988a973cacf1a7df9ce725c31b14776", 16).unwrap();
+        let cases = [
+            (Network::Mainnet, true, "1PRTTaJesdNovgne6Ehcdu1fpEdX7913CK"),
+            (Network::Mainnet, false, "14K1y4Epb341duzDmWsPniLyBh9EVh8jG3"),
+            (Network::Testnet, true, "n3wQkdPdgep4hoGFoofzTpDzgEEE3Rn6Nm"),
+            (Network::Testnet, false, "mipyG7KoQ4VGR2TqV5qmcdZJ3gjwPb7cRi"),
+            (Network::Signet, true, "n3wQkdPdgep4hoGFoofzTpDzgEEE3Rn6Nm"),
+            (Network::Regtest, false, "mipyG7KoQ4VGR2TqV5qmcdZJ3gjwPb7cRi"),
+        ];
+
+        for (network, compressed, expected) in cases {
+            let wallet = Wallet::new(private_key.clone(), network, compressed).unwrap();
+
+            assert_eq!(wallet.address(), expected);
+        }
+    }
 
+    #[test]
+    fn test_address_from_wif() {
+        // https://github.com/bitcoin/bitcoin/blob/master/test/functional/rpc_signmessage.py
+        let wallet = Wallet::from_wif("cUeKHd5orzT3mz8P9pxyREHfsWtVfgsfDjiZZBcjUBAaGk1BTj7N").unwrap();
 
-        let b58 = Base58::from_vec_u8(added_00_byte);
+        assert_eq!(wallet.address(), "mpLQjfK79b7CCV4VMJWEWAj5Mpx8Up5zxB");
+    }
 
-        // dbg!(b58);
-        // dbg!(rip_result);
+    #[test]
+    fn test_p2wpkh_address() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki#examples
+        // Signet shares testnet's HRP, so its address parses back as testnet.
+        let cases = [
+            (Network::Mainnet, Network::Mainnet, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+            (Network::Testnet, Network::Testnet, "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"),
+            (Network::Signet, Network::Testnet, "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"),
+            (Network::Regtest, Network::Regtest, "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080"),
+        ];
+
+        for (network, parsed_network, expected) in cases {
+            let wallet = Wallet::new(BigInt::from(1), network, true).unwrap();
+            let address = wallet.p2wpkh_address().unwrap();
+            let parsed = Address::from_str(&address).unwrap();
+
+            assert_eq!(address, expected);
+            assert_eq!(parsed.network(), parsed_network);
+            assert_eq!(parsed.address_type(), AddressType::P2wpkh(utils::hash160(&wallet.pub_key_compressed_bytes())));
+            assert_eq!(parsed.to_string(), address);
+        }
+    }
 
-        let expected_b58 = "14K1y4Epb341duzDmWsPniLyBh9EVh8jG3".to_string();
+    #[test]
+    fn test_p2wpkh_address_is_witness_version_0() {
+        let wallet = Wallet::from_wif("cUeKHd5orzT3mz8P9pxyREHfsWtVfgsfDjiZZBcjUBAaGk1BTj7N").unwrap();
+        let address = wallet.p2wpkh_address().unwrap();
+        let decoded = Bech32::decode(&address).unwrap();
+
+        assert_eq!(decoded.hrp, "tb");
+        assert_eq!(decoded.variant, Variant::Bech32);
+        assert_eq!(decoded.data[0], 0);
+        assert_eq!(Bech32::from_base32(&decoded.data[1..]), Ok(utils::hash160(&wallet.pub_key_compressed_bytes()).to_vec()));
+    }
 
-        assert_eq!(b58, expected_b58);
+    #[test]
+    fn test_p2wpkh_address_refuses_uncompressed_keys() {
+        let wallet = Wallet::new(BigInt::from(1), Network::Mainnet, false).unwrap();
+
+        assert_eq!(wallet.p2wpkh_address(), Err(WalletError::UncompressedPublicKey));
+    }
+
+    #[test]
+    fn test_testnet_wallet_from_raw_key() {
+        let private_key = BigInt::parse_bytes(b"3aba4162c7251c891207b747840551a71939b0de081f85c4e44cf7c13e41daa6", 16).unwrap();
+        let wallet = Wallet::new(private_key, Network::Testnet, true).unwrap();
+
+        assert_eq!(wallet.network(), Network::Testnet);
+        assert_eq!(wallet.wif_encoded_private_key(), "cPYrrSxKLYuPC2PMTjNtDbMaLK8k22nmWfXJDL8Q63sbw4ihZGFM");
+        assert_eq!(Address::from_str(&wallet.address()).unwrap().network(), Network::Testnet);
+        assert_eq!(Address::from_str(&wallet.p2wpkh_address().unwrap()).unwrap().network(), Network::Testnet);
+    }
+
+    #[test]
+    fn test_only_mainnet_emits_mainnet_addresses() {
+        for network in Network::ALL {
+            let wallet = Wallet::new(BigInt::from(0x1234_5678u64), network, true).unwrap();
+            let decoded = Base58::check_decode(&wallet.address()).unwrap();
+
+            assert_eq!(decoded.version == Network::Mainnet.p2pkh_version(), network == Network::Mainnet);
+            assert_eq!(wallet.p2wpkh_address().unwrap().starts_with("bc1"), network == Network::Mainnet);
+        }
     }
 }
\ No newline at end of file